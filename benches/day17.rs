@@ -0,0 +1,50 @@
+//! Day 17 execution engines: the instruction-at-a-time interpreter against the pre-decoded [Compiled] program, run
+//! serially and as a parallel batch. `cargo bench --bench day17`
+
+use aoc2024::day17::{parse, Register};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// A program of the puzzle's shape, outputting one value per 3 bits of A
+const PROGRAM: &str = "Register A: 0\nRegister B: 0\nRegister C: 0\n\nProgram: 2,4,1,3,7,5,4,1,1,3,0,3,5,5,3,0";
+
+fn a_values(n: i64) -> Vec<i64> {
+    // spread over 48 bits, so every run outputs 16 values
+    (0..n).map(|i| (1 << 45) + i * 0x1_2345_6789).collect()
+}
+
+fn engines(c: &mut Criterion) {
+    let machine = parse(PROGRAM);
+    let compiled = machine.compile();
+    let mut group = c.benchmark_group("day17");
+    for n in [1_000, 10_000] {
+        let a_values = a_values(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("interpreter", n), &a_values, |b, a_values| {
+            // a fresh machine per run, as the interpreter keeps its output and instruction pointer
+            b.iter(|| {
+                for a in a_values {
+                    let mut run = machine.clone();
+                    run.set_register(Register::A, *a);
+                    run.run();
+                    criterion::black_box(run.output().len());
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("compiled", n), &a_values, |b, a_values| {
+            let mut output = Vec::new();
+            b.iter(|| {
+                for a in a_values {
+                    compiled.run_into(*a, &mut output).unwrap();
+                    criterion::black_box(output.len());
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("compiled_batch", n), &a_values, |b, a_values| {
+            b.iter(|| criterion::black_box(compiled.run_batch(a_values)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
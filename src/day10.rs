@@ -20,13 +20,7 @@ impl<T: BufRead> From<T> for TrailMap {
 
 impl TrailMap {
     fn trailheads(&self) -> Vec<(i64, i64)> {
-        self.map
-            .data
-            .iter()
-            .enumerate()
-            .filter(|(_, v)| **v == b'0')
-            .map(|(i, _v)| self.map.coord(i as i64).unwrap().into())
-            .collect_vec()
+        self.map.find_all(&b'0').map(|c| c.into()).collect_vec()
     }
     fn count_reachable_from(&self, pos: &(i64, i64), needle: u8, visited: &mut Grid<bool>) -> u64 {
         if visited.get(pos) == Some(&true) {
@@ -39,13 +33,10 @@ impl TrailMap {
             return 1;
         }
         // adjacents that are +1
-        [(-1, 0), (1, 0), (0, -1), (0, 1)] // left, right, up, down
-            .iter()
-            .map(|(x_ofs, y_ofs)| (pos.0 + x_ofs, pos.1 + y_ofs)) // get target position
-            .map(|target_pos| (target_pos, self.map.get(&target_pos))) // get value at that position
-            .filter(|(_, val)| *val == Some(&(our_val + 1))) // only interested if it's our value + 1
-            .map(|(pos, _)| pos) // discard the value
-            .map(|pos| self.count_reachable_from(&pos, needle, visited))
+        self.map
+            .neighbors4(pos)
+            .filter(|(_, val)| **val == our_val + 1) // only interested if it's our value + 1
+            .map(|(pos, _)| self.count_reachable_from(&pos.into(), needle, visited))
             .sum()
     }
 
@@ -54,13 +45,10 @@ impl TrailMap {
         if *our_val == needle {
             return 1;
         }
-        [(-1, 0), (1, 0), (0, -1), (0, 1)] // left, right, up, down
-            .iter()
-            .map(|(x_ofs, y_ofs)| (pos.0 + x_ofs, pos.1 + y_ofs)) // get target position
-            .map(|target_pos| (target_pos, self.map.get(&target_pos))) // get value at that position
-            .filter(|(_, val)| *val == Some(&(our_val + 1))) // only interested if it's our value + 1
-            .map(|(pos, _)| pos) // discard the value
-            .map(|mov| self.count_paths_to(&mov, needle))
+        self.map
+            .neighbors4(pos)
+            .filter(|(_, val)| **val == our_val + 1) // only interested if it's our value + 1
+            .map(|(pos, _)| self.count_paths_to(&pos.into(), needle))
             .sum::<u64>()
     }
 }
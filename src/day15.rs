@@ -1,10 +1,44 @@
 use aoc_runner_derive::aoc;
-use grid::{AsCoord2d, Coord2d, Grid};
+use grid::{AsCoord2d, CellCodec, Coord2d, Grid};
 use itertools::Itertools;
-use std::{fmt::Display, io::Cursor, str::FromStr};
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Tile {
+    Wall,
+    Empty,
+    Box,
+    BoxLeft,
+    BoxRight,
+    Robot,
+}
+
+impl CellCodec for Tile {
+    fn decode(symbol: u8) -> Option<Self> {
+        match symbol {
+            b'#' => Some(Tile::Wall),
+            b'.' => Some(Tile::Empty),
+            b'O' => Some(Tile::Box),
+            b'[' => Some(Tile::BoxLeft),
+            b']' => Some(Tile::BoxRight),
+            b'@' => Some(Tile::Robot),
+            _ => None,
+        }
+    }
+    fn encode(&self) -> char {
+        match self {
+            Tile::Wall => '#',
+            Tile::Empty => '.',
+            Tile::Box => 'O',
+            Tile::BoxLeft => '[',
+            Tile::BoxRight => ']',
+            Tile::Robot => '@',
+        }
+    }
+}
 
 struct Warehouse {
-    map: Grid<u8>,
+    map: Grid<Tile>,
     robot_pos: Coord2d,
 }
 
@@ -26,28 +60,28 @@ impl Warehouse {
         if self.can_push(pos, dir) {
             let target = pos + dir.ofs();
             match self.map.get(&target).unwrap() {
-                b'#' => {}
-                b'.' => self.map.swap(target, pos),
-                b'O' => {
+                Tile::Wall => {}
+                Tile::Empty => self.map.swap(target, pos),
+                Tile::Box => {
                     self.push(&target, dir);
                     self.map.swap(target, pos);
                 }
-                b'[' | b']' if *dir == Move::Left || *dir == Move::Right => {
+                Tile::BoxLeft | Tile::BoxRight if *dir == Move::Left || *dir == Move::Right => {
                     self.push(&target, dir);
                     self.map.swap(target, pos)
                 }
-                b']' => {
+                Tile::BoxRight => {
                     // move both parts
                     self.push(&target, dir);
                     self.push(&(&target + (-1, 0)), dir);
                     self.map.swap(target, pos);
                 }
-                b'[' => {
+                Tile::BoxLeft => {
                     self.push(&target, dir);
                     self.push(&(&target + (1, 0)), dir);
                     self.map.swap(target, pos);
                 }
-                c => panic!("unexpected char {}", c),
+                Tile::Robot => panic!("unexpected second robot at {:?}", target),
             }
             return true;
         }
@@ -57,42 +91,37 @@ impl Warehouse {
     fn can_push(&mut self, pos: &Coord2d, dir: &Move) -> bool {
         let target = pos + dir.ofs();
         match self.map.get(&target).unwrap() {
-            b'#' => false,
-            b'.' => true,
-            b'O' => self.can_push(&target, dir),
-            b'[' | b']' if *dir == Move::Left || *dir == Move::Right => self.can_push(&target, dir),
-            b']' => self.can_push(&target, dir) && self.can_push(&(&target + (-1, 0)), dir),
-            b'[' => self.can_push(&target, dir) && self.can_push(&(&target + (1, 0)), dir),
-            c => panic!("unexpected char {}", c),
+            Tile::Wall => false,
+            Tile::Empty => true,
+            Tile::Box => self.can_push(&target, dir),
+            Tile::BoxLeft | Tile::BoxRight if *dir == Move::Left || *dir == Move::Right => self.can_push(&target, dir),
+            Tile::BoxRight => self.can_push(&target, dir) && self.can_push(&(&target + (-1, 0)), dir),
+            Tile::BoxLeft => self.can_push(&target, dir) && self.can_push(&(&target + (1, 0)), dir),
+            Tile::Robot => panic!("unexpected second robot at {:?}", target),
         }
     }
 
     fn embiggen(&mut self) {
-        let new_lines = (0..self.map.height())
-            .map(|r| self.map.row(r as i64).unwrap())
-            .map(|row| {
-                row.iter()
-                    .flat_map(|c| match c {
-                        b'#' => ['#', '#'],
-                        b'O' => ['[', ']'],
-                        b'.' => ['.', '.'],
-                        b'@' => ['@', '.'],
-                        c => panic!("unexpected character {}", c),
-                    })
-                    .collect::<String>()
-            })
-            .join("\n");
-        self.map = Grid::from(Cursor::new(new_lines.as_str()));
-        self.robot_pos = self.map.find(&b'@').unwrap().to_coord();
+        let mut map = Grid::with_shape(self.map.width() * 2, self.map.height(), Tile::Empty);
+        for (pos, tile) in self.map.iter_enumerated() {
+            let (left, right) = match tile {
+                Tile::Wall => (Tile::Wall, Tile::Wall),
+                Tile::Box => (Tile::BoxLeft, Tile::BoxRight),
+                Tile::Empty => (Tile::Empty, Tile::Empty),
+                Tile::Robot => (Tile::Robot, Tile::Empty),
+                t => panic!("unexpected tile {:?}", t),
+            };
+            map.set(&(pos.x * 2, pos.y), left);
+            map.set(&(pos.x * 2 + 1, pos.y), right);
+        }
+        self.map = map;
+        self.robot_pos = self.map.find(&Tile::Robot).unwrap().to_coord();
     }
 
     fn score(&self) -> i64 {
         self.map
-            .data
-            .iter()
-            .enumerate()
-            .filter(|(_, v)| **v == b'O' || **v == b'[')
-            .map(|(i, _)| self.map.coord(i as i64).unwrap().y() * 100 + self.map.coord(i as i64).unwrap().x())
+            .positions(|v| *v == Tile::Box || *v == Tile::BoxLeft)
+            .map(|pos| pos.y() * 100 + pos.x())
             .sum()
     }
 }
@@ -152,9 +181,9 @@ impl FromStr for MovePlan {
 fn parse(input: &str) -> (Warehouse, MovePlan) {
     let lines = input.lines().collect_vec();
     let parts = lines.split(|l| l.is_empty()).map(|ls| ls.join("\n")).collect_vec();
-    let map: Grid<u8> = parts[0].parse().unwrap();
+    let map = Grid::<Tile>::decode(parts[0].as_bytes()).unwrap();
     let wh = Warehouse {
-        robot_pos: map.find(&b'@').unwrap().to_coord(),
+        robot_pos: map.find(&Tile::Robot).unwrap().to_coord(),
         map,
     };
     let moves = parts[1].parse().unwrap();
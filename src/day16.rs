@@ -1,16 +1,15 @@
 use aoc_runner_derive::aoc;
-use grid::{Coord2d, Grid};
-use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
-    str::FromStr,
+use grid::{
+    search::{self, NoopTrack, ShortestPaths},
+    Grid,
 };
+use std::str::FromStr;
 
 type CoordType = i16;
-type Coord = (CoordType, CoordType);
+pub type Coord = (CoordType, CoordType);
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Ord, PartialOrd)]
-enum FacingDirection {
+pub enum FacingDirection {
     East,
     South,
     West,
@@ -18,15 +17,7 @@ enum FacingDirection {
 }
 
 impl FacingDirection {
-    fn ofs(&self) -> (CoordType, CoordType) {
-        match self {
-            FacingDirection::East => (1, 0),
-            FacingDirection::South => (0, 1),
-            FacingDirection::West => (-1, 0),
-            FacingDirection::North => (0, -1),
-        }
-    }
-    fn reachable(&self) -> &[FacingDirection; 3] {
+    fn reachable(&self) -> &'static [FacingDirection; 3] {
         // Can move perpendicularly or the same direction, backwards would always increase path cost
         match self {
             FacingDirection::East => &[FacingDirection::East, FacingDirection::North, FacingDirection::South],
@@ -35,50 +26,43 @@ impl FacingDirection {
             FacingDirection::North => &[FacingDirection::North, FacingDirection::East, FacingDirection::West],
         }
     }
-}
-
-#[derive(Clone, Eq, PartialEq, Debug)]
-struct State {
-    cost: usize,
-    position: Coord,
-    facing: FacingDirection,
-}
-
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other
-            .cost
-            .cmp(&self.cost)
-            .then_with(|| self.position.cmp(&other.position))
-            .then_with(|| self.facing.cmp(&other.facing))
+    /// The offsets of the [FacingDirection::reachable] moves, in the same order, as a [Grid::neighbors_with] stencil
+    fn reachable_stencil(&self) -> &'static [(i64, i64); 3] {
+        match self {
+            FacingDirection::East => &[(1, 0), (0, -1), (0, 1)],
+            FacingDirection::West => &[(-1, 0), (0, -1), (0, 1)],
+            FacingDirection::South => &[(0, 1), (1, 0), (-1, 0)],
+            FacingDirection::North => &[(0, -1), (1, 0), (-1, 0)],
+        }
     }
-}
-
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    fn arrow(&self) -> u8 {
+        match self {
+            FacingDirection::East => b'>',
+            FacingDirection::South => b'v',
+            FacingDirection::West => b'<',
+            FacingDirection::North => b'^',
+        }
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-struct PathState {
-    state: State,
-    path: Vec<Coord>,
+/// Scoring of the reindeer's moves
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MoveCosts {
+    /// Moving forward one tile
+    pub step: usize,
+    /// Rotating a quarter turn
+    pub turn: usize,
 }
 
-impl Ord for PathState {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.state.cmp(&other.state)
-    }
-}
-impl PartialOrd for PathState {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Default for MoveCosts {
+    fn default() -> Self {
+        Self { step: 1, turn: 1000 }
     }
 }
 
-struct Maze {
+pub struct Maze {
     map: Grid<u8>,
+    costs: MoveCosts,
 }
 
 impl FromStr for Maze {
@@ -86,130 +70,81 @@ impl FromStr for Maze {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let map: Grid<u8> = s.parse()?;
 
-        Ok(Self { map })
+        Ok(Self {
+            map,
+            costs: MoveCosts::default(),
+        })
     }
 }
 
+pub type Reindeer = (Coord, FacingDirection);
+
 impl Maze {
-    fn valid_moves<'a>(&'a self, state: &'a State) -> impl Iterator<Item = State> + use<'a> {
-        let reachable = state.facing.reachable();
-        reachable
-            .iter()
-            .map(|dir| (dir, (state.position.0 + dir.ofs().0, state.position.1 + dir.ofs().1)))
-            .filter(|(_, pos)| self.map.get(pos).is_some_and(|c| *c != b'#'))
-            .map(|(dir, pos)| State {
-                facing: *dir,
-                position: pos,
-                cost: if *dir == state.facing {
-                    state.cost + 1
+    pub fn with_costs(self, costs: MoveCosts) -> Self {
+        Self { costs, ..self }
+    }
+    fn valid_moves(&self, &(position, facing): &Reindeer) -> impl Iterator<Item = (Reindeer, usize)> + '_ {
+        let stencil = facing.reachable_stencil();
+        self.map
+            .neighbors_with(&position, stencil)
+            .filter(|(_, c)| **c != b'#')
+            .map(move |(pos, _)| {
+                let ofs = (pos.x - position.0 as i64, pos.y - position.1 as i64);
+                let dir = facing.reachable()[stencil.iter().position(|s| *s == ofs).unwrap()];
+                let cost = if dir == facing {
+                    self.costs.step
                 } else {
-                    state.cost + 1001
-                },
+                    self.costs.step + self.costs.turn
+                };
+                (((pos.x as CoordType, pos.y as CoordType), dir), cost)
             })
     }
+    fn endpoints(&self) -> (Coord, Coord) {
+        let start = self.map.find(&b'S').expect("can't find start");
+        let finish = self.map.find(&b'E').expect("can't find finish");
+        (
+            (start.x as CoordType, start.y as CoordType),
+            (finish.x as CoordType, finish.y as CoordType),
+        )
+    }
     fn dijkstra(&self) -> usize {
-        let Coord2d {x: start_x, y: start_y} = self.map.find(&b'S').expect("can't find start");
-        let start = (start_x as CoordType, start_y as CoordType);
-
-        let Coord2d {x: finish_x, y: finish_y} = self.map.find(&b'E').expect("can't find finish");
-        let finish = (finish_x as CoordType, finish_y as CoordType);
-
-        let mut distances = HashMap::new();
-        let mut queue = BinaryHeap::new();
-
-        distances.insert((start, FacingDirection::East), 0);
-        queue.push(State {
-            cost: 0,
-            position: start,
-            facing: FacingDirection::East,
-        });
-
-        while let Some(state) = queue.pop() {
-            if state.position == finish {
-                return state.cost;
-            }
-
-            if distances
-                .get(&(state.position, state.facing))
-                .is_some_and(|v| state.cost > *v)
-            {
-                continue;
-            }
-
-            for new_state in self.valid_moves(&state) {
-                if distances
-                    .get(&(new_state.position, new_state.facing))
-                    .is_none_or(|best_cost| new_state.cost < *best_cost)
-                {
-                    distances.insert((new_state.position, new_state.facing), new_state.cost);
-                    queue.push(new_state);
-                }
-            }
-        }
-        usize::MAX
+        let (start, finish) = self.endpoints();
+        search::dijkstra::<_, _, NoopTrack, _, _, _>(
+            (start, FacingDirection::East),
+            |state| self.valid_moves(state),
+            |(position, _)| *position == finish,
+        )
+        .map_or(usize::MAX, |(cost, _)| cost)
     }
-    fn path_dijkstra(&mut self) -> (usize, Vec<Vec<Coord>>) {
-        let Coord2d {x: start_x, y: start_y} = self.map.find(&b'S').expect("can't find start");
-        let start = (start_x as CoordType, start_y as CoordType);
-
-        let Coord2d {x: finish_x, y: finish_y} = self.map.find(&b'E').expect("can't find finish");
-        let finish = (finish_x as CoordType, finish_y as CoordType);
-
-        let mut distances = HashMap::new();
-        let mut queue = BinaryHeap::with_capacity(self.map.data.len());
-        let mut best_paths = Vec::new();
-        let mut best_cost = usize::MAX;
-
-        distances.insert((start, FacingDirection::East), 0);
-        queue.push(PathState {
-            state: State {
-                cost: 0,
-                position: start,
-                facing: FacingDirection::East,
-            },
-            path: Vec::new(),
-        });
-
-        while let Some(PathState { state, mut path }) = queue.pop() {
-            if distances
-                .get(&(state.position, state.facing))
-                .is_some_and(|v| state.cost > *v)
-            {
-                continue;
-            }
-            if state.position == finish {
-                match state.cost.cmp(&best_cost) {
-                    Ordering::Less => {
-                        path.push(state.position);
-                        best_paths.clear();
-                        best_paths.push(path);
-                        best_cost = state.cost
-                    }
-                    Ordering::Equal => {
-                        path.push(state.position);
-                        best_paths.push(path);
-                    }
-                    _ => {}
-                }
-                continue;
-            }
-
-            for new_state in self.valid_moves(&state) {
-                if distances
-                    .get(&(new_state.position, new_state.facing))
-                    .is_none_or(|best_cost| new_state.cost <= *best_cost)
-                {
-                    let mut new_path = path.clone();
-                    new_path.push(state.position);
-                    distances.insert((new_state.position, new_state.facing), new_state.cost);
-                    queue.push(PathState {
-                        state: new_state,
-                        path: new_path,
-                    });
-                }
+    fn path_dijkstra(&self) -> ShortestPaths<Reindeer, usize> {
+        let (start, finish) = self.endpoints();
+        search::dijkstra_all(
+            (start, FacingDirection::East),
+            |state| self.valid_moves(state),
+            |(position, _)| *position == finish,
+        )
+        .expect("no path to finish")
+    }
+    /// The `k` cheapest distinct routes to the finish with their scores, best first
+    pub fn k_shortest(&self, k: usize) -> Vec<(usize, Vec<Reindeer>)> {
+        let (start, finish) = self.endpoints();
+        search::k_shortest_paths(
+            (start, FacingDirection::East),
+            |state| self.valid_moves(state),
+            |(position, _)| *position == finish,
+            k,
+        )
+    }
+    /// Draw `path` over the maze, marking each tile with the direction the reindeer faces on arriving there. The start
+    /// and finish keep their markers.
+    pub fn render_path(&self, path: &[Reindeer]) -> String {
+        let mut map = self.map.clone();
+        for (position, facing) in path {
+            if map.get(position).is_some_and(|c| *c == b'.') {
+                map.set(position, facing.arrow());
             }
         }
-        (best_cost, best_paths)
+        map.to_string()
     }
 }
 
@@ -225,12 +160,12 @@ pub fn part1(input: &str) -> usize {
 
 #[aoc(day16, part2)]
 pub fn part2(input: &str) -> usize {
-    let mut maze = parse(input);
+    let maze = parse(input);
     let best_paths = maze.path_dijkstra();
 
     let mut path_map = maze.map.same_shape(false);
-    for tile in best_paths.1.iter().flatten() {
-        path_map.set(tile, true);
+    for (tile, _) in best_paths.nodes() {
+        path_map.set(&tile, true);
     }
     path_map.count(&true)
 }
@@ -291,4 +226,37 @@ mod tests {
     fn part2_example2() {
         assert_eq!(part2(EXAMPLE2), 64);
     }
+
+    #[test]
+    fn k_shortest() {
+        let maze = parse(EXAMPLE1);
+        let routes = maze.k_shortest(4);
+        assert_eq!(routes.len(), 4);
+        // three routes tie for the best score
+        assert_eq!(routes.iter().map(|(cost, _)| *cost).collect::<Vec<_>>()[..3], [7036; 3]);
+        assert!(routes[3].0 > 7036);
+        let best = maze.path_dijkstra().paths();
+        for (_, path) in &routes[..3] {
+            assert!(best.contains(path));
+        }
+
+        let cheap_turns = parse(EXAMPLE1).with_costs(MoveCosts { step: 1, turn: 0 });
+        let routes = cheap_turns.k_shortest(2);
+        // with free turns the score is just the number of steps
+        assert_eq!(routes[0].0, routes[0].1.len() - 1);
+        assert!(routes[0].0 < 7036);
+        assert!(routes[1].0 >= routes[0].0);
+    }
+
+    #[test]
+    fn render() {
+        let maze = parse(EXAMPLE1);
+        let (_, path) = &maze.k_shortest(1)[0];
+        let rendered = maze.render_path(path);
+        let lines = rendered.trim_end().lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 15);
+        assert!(lines[13].starts_with("#S"));
+        assert_eq!(rendered.matches(['>', 'v', '<', '^']).count(), path.len() - 2);
+        assert!(lines[1].ends_with("E#"));
+    }
 }
@@ -1,9 +1,12 @@
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
+use rayon::prelude::*;
 use regex::Regex;
+use rustc_hash::FxHashMap;
+use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy)]
-enum Register {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Register {
     A = 0,
     B = 1,
     C = 2,
@@ -19,8 +22,18 @@ impl From<usize> for Register {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Opcode {
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Register::A => "A",
+            Register::B => "B",
+            Register::C => "C",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Opcode {
     Adv = 0,
     Bxl = 1,
     Bst = 2,
@@ -47,21 +60,53 @@ impl From<i64> for Opcode {
     }
 }
 
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Opcode::Adv => "adv",
+            Opcode::Bxl => "bxl",
+            Opcode::Bst => "bst",
+            Opcode::Jnz => "jnz",
+            Opcode::Bxc => "bxc",
+            Opcode::Out => "out",
+            Opcode::Bdv => "bdv",
+            Opcode::Cdv => "cdv",
+        })
+    }
+}
+
 impl Opcode {
+    fn takes_combo(&self) -> bool {
+        matches!(self, Self::Adv | Self::Bst | Self::Out | Self::Bdv | Self::Cdv)
+    }
     fn interp_operand(&self, value: i64) -> Operand {
         match self {
-            Self::Adv | Self::Bst | Self::Out | Self::Bdv | Self::Cdv => Operand::new_combo(value),
-            Self::Bxl | Self::Jnz => Operand::Literal(value),
-            Self::Bxc => Operand::Ignore,
+            Self::Bxc => Operand::Ignore(value),
+            _ if self.takes_combo() => Operand::new_combo(value),
+            _ => Operand::Literal(value),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Operand {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operand {
     Literal(i64),
     Load(Register),
-    Ignore,
+    /// The operand of `bxc`, which is never read but is kept so programs round trip
+    Ignore(i64),
+    /// Combo operand 7, which is invalid to execute but can still appear in a program (e.g. as data)
+    Reserved,
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Literal(i) => write!(f, "{}", i),
+            Operand::Load(reg) => write!(f, "{}", reg),
+            Operand::Ignore(i) => write!(f, "{}", i),
+            Operand::Reserved => f.write_str("<reserved>"),
+        }
+    }
 }
 
 impl Operand {
@@ -71,7 +116,7 @@ impl Operand {
             4 => Operand::Load(Register::A),
             5 => Operand::Load(Register::B),
             6 => Operand::Load(Register::C),
-            7 => panic!("reserved combo operand 7"),
+            7 => Operand::Reserved,
             i => panic!("invalid combo operand {}", i),
         }
     }
@@ -79,12 +124,32 @@ impl Operand {
         match self {
             Self::Literal(i) => i,
             Self::Load(reg) => *m.registers.load(reg),
-            Self::Ignore => panic!("can't read ignored operand"),
+            Self::Ignore(_) => panic!("can't read ignored operand"),
+            Self::Reserved => panic!("reserved combo operand 7"),
+        }
+    }
+}
+
+/// `num / 2^shift`, rounded down. Shifts wider than a register leave only the sign, and negative shifts (only
+/// reachable through negative registers) multiply by `2^-shift`, wrapping on overflow. Shared by every engine so
+/// they agree on values the puzzle never produces.
+fn divide_pow2(num: i64, shift: i64) -> i64 {
+    if shift >= 0 {
+        num >> shift.min(63)
+    } else {
+        match shift.unsigned_abs() {
+            s if s >= 64 => 0,
+            s => num.wrapping_shl(s as u32),
         }
     }
 }
 
-#[derive(Debug)]
+/// `val` modulo 8, which keeps negative values in 0-7 to match the rounding of [divide_pow2]
+fn modulo8(val: i64) -> i64 {
+    val & 7
+}
+
+#[derive(Debug, Clone)]
 struct RegisterFile<const SIZE: usize, T> {
     file: [T; SIZE],
 }
@@ -96,18 +161,31 @@ impl<T: Clone + From<i64>, const SIZE: usize> RegisterFile<SIZE, T> {
     fn store(&mut self, reg: Register, val: T) {
         self.file[reg as usize] = val;
     }
-    fn reset(&mut self) {
-        self.file.fill(0.into());
-    }
 }
 
-#[derive(Debug, Copy, Clone)]
-struct Instruction {
-    opcode: Opcode,
-    operand: Operand,
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operand: Operand,
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.operand {
+            Operand::Ignore(0) => write!(f, "{}", self.opcode),
+            operand => write!(f, "{} {}", self.opcode, operand),
+        }
+    }
 }
 
 impl Instruction {
+    fn decode(opcode: i64, operand: i64) -> Self {
+        let opcode: Opcode = opcode.into();
+        Instruction {
+            operand: opcode.interp_operand(operand),
+            opcode,
+        }
+    }
     fn exec(&self, m: &mut Machine) {
         match self.opcode {
             Opcode::Adv => self.adv(m),
@@ -121,9 +199,8 @@ impl Instruction {
         }
     }
     fn adv(&self, m: &mut Machine) {
-        let num = m.registers.load(Register::A);
-        let denom = 1 << self.operand.value(m);
-        m.registers.store(Register::A, num / denom);
+        let num = *m.registers.load(Register::A);
+        m.registers.store(Register::A, divide_pow2(num, self.operand.value(m)));
         m.advance();
     }
     fn bxl(&self, m: &mut Machine) {
@@ -133,7 +210,7 @@ impl Instruction {
         m.advance();
     }
     fn bst(&self, m: &mut Machine) {
-        m.registers.store(Register::B, self.operand.value(m) % 8);
+        m.registers.store(Register::B, modulo8(self.operand.value(m)));
         m.advance();
     }
     fn jnz(&self, m: &mut Machine) {
@@ -150,58 +227,213 @@ impl Instruction {
         m.advance();
     }
     fn out(&self, m: &mut Machine) {
-        m.out_file.push(self.operand.value(m) % 8);
+        m.out_file.push(modulo8(self.operand.value(m)));
         m.advance();
     }
     fn bdv(&self, m: &mut Machine) {
-        let num = m.registers.load(Register::A);
-        let denom = 1 << self.operand.value(m);
-        m.registers.store(Register::B, num / denom);
+        let num = *m.registers.load(Register::A);
+        m.registers.store(Register::B, divide_pow2(num, self.operand.value(m)));
         m.advance();
     }
     fn cdv(&self, m: &mut Machine) {
-        let num = m.registers.load(Register::A);
-        let denom = 1 << self.operand.value(m);
-        m.registers.store(Register::C, num / denom);
+        let num = *m.registers.load(Register::A);
+        m.registers.store(Register::C, divide_pow2(num, self.operand.value(m)));
         m.advance();
     }
 }
 
-#[derive(Debug)]
+/// Why execution stopped without halting or reaching a requested stop
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Fault {
+    /// The instruction at `ip` uses the reserved combo operand 7
+    ReservedOperand { ip: usize },
+    /// `steps` instructions were executed without stopping, the program may loop forever
+    StepLimit { steps: usize },
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::ReservedOperand { ip } => write!(f, "reserved combo operand at ip {}", ip),
+            Fault::StepLimit { steps } => write!(f, "no stop after {} steps", steps),
+        }
+    }
+}
+
+/// Where a debugger run stopped
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Stop {
+    /// The instruction pointer ran off the end of the program
+    Halted,
+    /// An `out` instruction produced this value
+    Output(i64),
+    /// The instruction pointer reached a breakpoint, before executing the instruction there
+    Breakpoint(usize),
+}
+
+/// The effect of one executed instruction
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TraceEntry {
+    /// Address of the instruction
+    pub ip: usize,
+    pub instruction: Instruction,
+    /// Registers A, B and C after execution
+    pub registers: [i64; 3],
+    /// The value written by an `out` instruction
+    pub output: Option<i64>,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>3}: {:<6} A={} B={} C={}",
+            self.ip,
+            self.instruction.to_string(),
+            self.registers[0],
+            self.registers[1],
+            self.registers[2]
+        )?;
+        if let Some(out) = self.output {
+            write!(f, " -> {}", out)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Machine {
     registers: RegisterFile<3, i64>,
-    program: Vec<Instruction>,
     program_raw: Vec<i64>,
+    /// Address of the next instruction's opcode in `program_raw`
     ip: usize,
     out_file: Vec<i64>,
 }
 
 impl Machine {
-    fn run(&mut self) {
-        let program = self.program.clone();
-        while let Some(inst) = program.get(self.ip) {
+    /// The instruction at `ip`, or `None` if the machine has halted. Instructions are decoded as they're fetched,
+    /// since a jump to an odd address reads the program with a different alignment.
+    fn fetch(&self) -> Option<Instruction> {
+        match self.program_raw.get(self.ip..self.ip + 2) {
+            Some(&[opcode, operand]) => Some(Instruction::decode(opcode, operand)),
+            _ => None,
+        }
+    }
+    /// Run until halting. Unlike the debugger methods this neither checks for faults nor limits the step count.
+    pub fn run(&mut self) {
+        while let Some(inst) = self.fetch() {
             inst.exec(self);
         }
     }
     fn advance(&mut self) {
-        self.ip += 1;
+        self.ip += 2;
     }
     fn jump(&mut self, addr: usize) {
         self.ip = addr;
     }
-    fn reset(&mut self) {
-        self.registers.reset();
-        self.ip = 0;
-        self.out_file.clear();
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+    pub fn register(&self, reg: Register) -> i64 {
+        *self.registers.load(reg)
+    }
+    pub fn set_register(&mut self, reg: Register, val: i64) {
+        self.registers.store(reg, val);
+    }
+    /// Everything output so far
+    pub fn output(&self) -> &[i64] {
+        &self.out_file
+    }
+
+    /// The program as (address, instruction) pairs, reading every opcode/operand pair from address 0. A trailing
+    /// unpaired opcode is never executed, so it's omitted.
+    pub fn disassembly(&self) -> Vec<(usize, Instruction)> {
+        self.program_raw
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(i, pair)| (i * 2, Instruction::decode(pair[0], pair[1])))
+            .collect()
+    }
+    /// The program listing, one `address: mnemonic operand` line per instruction
+    pub fn disassemble(&self) -> String {
+        self.disassembly()
+            .iter()
+            .map(|(addr, inst)| format!("{:>3}: {}\n", addr, inst))
+            .collect()
+    }
+
+    /// Execute a single instruction, or return `None` if the machine has halted
+    pub fn step(&mut self) -> Result<Option<TraceEntry>, Fault> {
+        let Some(instruction) = self.fetch() else {
+            return Ok(None);
+        };
+        if instruction.operand == Operand::Reserved {
+            return Err(Fault::ReservedOperand { ip: self.ip });
+        }
+        let ip = self.ip;
+        let out_len = self.out_file.len();
+        instruction.exec(self);
+        Ok(Some(TraceEntry {
+            ip,
+            instruction,
+            registers: self.registers.file,
+            output: self.out_file.get(out_len).copied(),
+        }))
+    }
+    /// Run until halting, recording every executed instruction. Fails if `max_steps` is exceeded.
+    pub fn trace(&mut self, max_steps: usize) -> Result<Vec<TraceEntry>, Fault> {
+        let mut trace = Vec::new();
+        self.run_until(max_steps, |entry, _| {
+            trace.push(*entry);
+            None
+        })?;
+        Ok(trace)
+    }
+    /// Run until the next output, or until halting
+    pub fn run_until_output(&mut self, max_steps: usize) -> Result<Stop, Fault> {
+        self.run_until(max_steps, |entry, _| entry.output.map(Stop::Output))
+    }
+    /// Run until the instruction pointer reaches `ip`, or until halting. At least one instruction is executed, so
+    /// repeated calls stop at successive visits.
+    pub fn run_until_ip(&mut self, ip: usize, max_steps: usize) -> Result<Stop, Fault> {
+        self.run_until_breakpoints(&[ip], max_steps)
+    }
+    /// Run until the instruction pointer reaches any of `breakpoints`, or until halting
+    pub fn run_until_breakpoints(&mut self, breakpoints: &[usize], max_steps: usize) -> Result<Stop, Fault> {
+        self.run_until(max_steps, |_, next_ip| {
+            breakpoints.contains(&next_ip).then_some(Stop::Breakpoint(next_ip))
+        })
+    }
+    /// Step until `stop` returns a [Stop] for an executed instruction (given the address of the next one), or the
+    /// machine halts
+    fn run_until<F: FnMut(&TraceEntry, usize) -> Option<Stop>>(
+        &mut self,
+        max_steps: usize,
+        mut stop: F,
+    ) -> Result<Stop, Fault> {
+        for _ in 0..max_steps {
+            match self.step()? {
+                None => return Ok(Stop::Halted),
+                Some(entry) => {
+                    if let Some(reason) = stop(&entry, self.ip) {
+                        return Ok(reason);
+                    }
+                }
+            }
+        }
+        if self.fetch().is_none() {
+            return Ok(Stop::Halted);
+        }
+        Err(Fault::StepLimit { steps: max_steps })
     }
 }
 
-fn parse(input: &str) -> Machine {
-    let reg_re = Regex::new(r"Register ([ABC]): (\d+)").unwrap();
+pub fn parse(input: &str) -> Machine {
+    let reg_re = Regex::new(r"Register ([ABC]): (-?\d+)").unwrap();
     let prog_re = Regex::new(r"Program: ((\d+,)*\d+)").unwrap();
 
     let mut registers: RegisterFile<3, i64> = RegisterFile { file: [0; 3] };
-    let mut program = Vec::new();
     let mut program_raw = Vec::new();
     for line in input.lines() {
         if let Some(caps) = reg_re.captures(line) {
@@ -211,30 +443,386 @@ fn parse(input: &str) -> Machine {
             continue;
         }
         if let Some(caps) = prog_re.captures(line) {
-            let instructions = caps[1].split(',');
-            for (inst, operand) in instructions.tuples() {
-                let opcode = inst.parse::<i64>().unwrap();
-                let operand = operand.parse::<i64>().unwrap();
-                program_raw.push(opcode);
-                program_raw.push(operand);
-                let opcode: Opcode = opcode.into();
-                program.push(Instruction {
-                    operand: opcode.interp_operand(operand),
-                    opcode,
-                });
-            }
+            program_raw.extend(caps[1].split(',').map(|v| v.parse::<i64>().unwrap()));
         }
     }
 
     Machine {
         registers,
-        program,
         program_raw,
         out_file: Vec::new(),
         ip: 0,
     }
 }
 
+/// What's wrong with a line of assembler source
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    MissingOperand,
+    /// Text after the operand
+    TrailingInput(String),
+    /// An operand that isn't a number (or register, or label) the instruction accepts
+    InvalidOperand(String),
+    /// Combo operand 7, which is reserved and crashes the machine
+    ReservedOperand,
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    /// Jump targets are 3-bit literals, so labels past address 7 can't be reached
+    LabelOutOfRange {
+        label: String,
+        address: usize,
+    },
+    /// The program has an odd number of values, so the last opcode has no operand
+    OddLength,
+}
+
+/// An assembler error on a 1-based source line
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic {:?}", m),
+            AsmErrorKind::MissingOperand => f.write_str("missing operand"),
+            AsmErrorKind::TrailingInput(s) => write!(f, "unexpected {:?} after operand", s),
+            AsmErrorKind::InvalidOperand(s) => write!(f, "invalid operand {:?}", s),
+            AsmErrorKind::ReservedOperand => f.write_str("combo operand 7 is reserved"),
+            AsmErrorKind::UnknownLabel(l) => write!(f, "unknown label {:?}", l),
+            AsmErrorKind::DuplicateLabel(l) => write!(f, "label {:?} is already defined", l),
+            AsmErrorKind::LabelOutOfRange { label, address } => {
+                write!(
+                    f,
+                    "label {:?} is at address {}, jump targets must be 0-7",
+                    label, address
+                )
+            }
+            AsmErrorKind::OddLength => f.write_str("program ends with an opcode and no operand"),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assembled register initialisation and program, rendered in the puzzle's input format by [Display]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Assembly {
+    pub registers: [i64; 3],
+    pub program: Vec<i64>,
+}
+
+impl Display for Assembly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (reg, val) in ["A", "B", "C"].iter().zip(self.registers) {
+            writeln!(f, "Register {}: {}", reg, val)?;
+        }
+        write!(f, "\nProgram: {}", self.program.iter().join(","))
+    }
+}
+
+/// A program value whose label may not be defined yet
+enum AsmValue<'a> {
+    Value(i64),
+    Label(&'a str),
+}
+
+/// Assemble mnemonic source into a program. Each line holds any of:
+///
+/// - `label:` definitions, naming the address of the next value
+/// - an instruction such as `adv 3`, `out B`, `jnz label` or `bxc`, with combo operands written as `0`-`3` or `A`-`C`.
+///   `bxc` ignores its operand, which defaults to 0.
+/// - `A = 729` to initialise a register
+/// - `.data 1, 2` to emit raw values
+/// - a `;` comment
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    let mut registers = [0; 3];
+    let mut values: Vec<(usize, AsmValue)> = Vec::new();
+    let mut labels: FxHashMap<&str, usize> = FxHashMap::default();
+
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let err = |kind| AsmError { line: line_no, kind };
+        let mut rest = line.split(';').next().unwrap().trim();
+
+        while let Some((label, after)) = rest.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            if labels.insert(label, values.len()).is_some() {
+                return Err(err(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            rest = after.trim();
+        }
+        if rest.is_empty() {
+            continue;
+        }
+
+        if let Some((reg, val)) = rest.split_once('=') {
+            let reg = match reg.trim() {
+                "A" => Register::A,
+                "B" => Register::B,
+                "C" => Register::C,
+                r => return Err(err(AsmErrorKind::InvalidOperand(r.to_string()))),
+            };
+            let val = val.trim();
+            registers[reg as usize] = val
+                .parse()
+                .map_err(|_| err(AsmErrorKind::InvalidOperand(val.to_string())))?;
+            continue;
+        }
+
+        if let Some(data) = rest.strip_prefix(".data") {
+            let data = data.trim();
+            if data.is_empty() {
+                return Err(err(AsmErrorKind::MissingOperand));
+            }
+            for item in data.split(',').map(str::trim) {
+                match item.parse::<i64>() {
+                    Ok(v) if (0..8).contains(&v) => values.push((line_no, AsmValue::Value(v))),
+                    _ => return Err(err(AsmErrorKind::InvalidOperand(item.to_string()))),
+                }
+            }
+            continue;
+        }
+
+        let mut tokens = rest.split_whitespace();
+        let mnemonic = tokens.next().unwrap();
+        let operand = tokens.next();
+        if let Some(extra) = tokens.next() {
+            return Err(err(AsmErrorKind::TrailingInput(extra.to_string())));
+        }
+
+        let opcode = (0..8)
+            .map(Opcode::from)
+            .find(|op| op.to_string() == mnemonic)
+            .ok_or_else(|| err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())))?;
+        let invalid = |s: &str| err(AsmErrorKind::InvalidOperand(s.to_string()));
+        let operand = match (opcode.takes_combo(), operand) {
+            (_, None) if opcode == Opcode::Bxc => AsmValue::Value(0),
+            (_, Some(s)) if opcode == Opcode::Bxc => match s.parse::<i64>() {
+                Ok(v) if (0..8).contains(&v) => AsmValue::Value(v),
+                _ => return Err(invalid(s)),
+            },
+            (_, None) => return Err(err(AsmErrorKind::MissingOperand)),
+            (false, Some(s)) => match s.parse::<i64>() {
+                Ok(v) if (0..8).contains(&v) => AsmValue::Value(v),
+                Ok(_) => return Err(invalid(s)),
+                Err(_) if opcode == Opcode::Jnz => AsmValue::Label(s),
+                Err(_) => return Err(invalid(s)),
+            },
+            (true, Some(s)) => match s {
+                "A" => AsmValue::Value(4),
+                "B" => AsmValue::Value(5),
+                "C" => AsmValue::Value(6),
+                _ => match s.parse::<i64>() {
+                    Ok(7) => return Err(err(AsmErrorKind::ReservedOperand)),
+                    Ok(v) if (0..7).contains(&v) => AsmValue::Value(v),
+                    _ => return Err(invalid(s)),
+                },
+            },
+        };
+        values.push((line_no, AsmValue::Value(opcode as i64)));
+        values.push((line_no, operand));
+    }
+
+    if !values.len().is_multiple_of(2) {
+        return Err(AsmError {
+            line: values.last().unwrap().0,
+            kind: AsmErrorKind::OddLength,
+        });
+    }
+    let program = values
+        .into_iter()
+        .map(|(line, value)| match value {
+            AsmValue::Value(v) => Ok(v),
+            AsmValue::Label(label) => match labels.get(label) {
+                Some(&address) if address < 8 => Ok(address as i64),
+                Some(&address) => Err(AsmError {
+                    line,
+                    kind: AsmErrorKind::LabelOutOfRange {
+                        label: label.to_string(),
+                        address,
+                    },
+                }),
+                None => Err(AsmError {
+                    line,
+                    kind: AsmErrorKind::UnknownLabel(label.to_string()),
+                }),
+            },
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Assembly { registers, program })
+}
+
+/// Where a compiled instruction reads a combo operand from
+#[derive(Debug, Clone, Copy)]
+enum Src {
+    Literal(i64),
+    Register(usize),
+}
+
+/// A pre-decoded instruction, with operands resolved and jump targets bounds-checked
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Adv(Src),
+    Bxl(i64),
+    Bst(Src),
+    Jnz(usize),
+    Bxc,
+    Out(Src),
+    Bdv(Src),
+    Cdv(Src),
+    /// An instruction using the reserved combo operand, which faults if executed
+    Reserved,
+}
+
+/// How many times [Compiled] runs may pass through the whole program before failing with [Fault::StepLimit], unless
+/// [Compiled::with_step_limit] says otherwise. A loop that shifts A right each time round finishes well within this.
+const COMPILED_PASSES: usize = 64;
+
+/// A program compiled to a flat table of [Op]s, one per byte address so jumps to odd addresses work, for evaluating
+/// many initial values of A without re-decoding or allocating per run
+#[derive(Debug, Clone)]
+pub struct Compiled {
+    ops: Vec<Op>,
+    registers: [i64; 3],
+    max_steps: usize,
+}
+
+impl Machine {
+    /// Compile the program, keeping the current B and C registers as the initial values for every run. Runs are
+    /// limited to [COMPILED_PASSES] times the program's length in steps, so a batch can't hang on a value of A for
+    /// which the program never halts.
+    pub fn compile(&self) -> Compiled {
+        let ops = self
+            .program_raw
+            .windows(2)
+            .map(|pair| {
+                let inst = Instruction::decode(pair[0], pair[1]);
+                let src = match inst.operand {
+                    Operand::Literal(v) => Src::Literal(v),
+                    Operand::Load(reg) => Src::Register(reg as usize),
+                    Operand::Ignore(_) => Src::Literal(0),
+                    Operand::Reserved => return Op::Reserved,
+                };
+                match inst.opcode {
+                    Opcode::Adv => Op::Adv(src),
+                    Opcode::Bxl => Op::Bxl(pair[1]),
+                    Opcode::Bst => Op::Bst(src),
+                    Opcode::Jnz => Op::Jnz(pair[1] as usize),
+                    Opcode::Bxc => Op::Bxc,
+                    Opcode::Out => Op::Out(src),
+                    Opcode::Bdv => Op::Bdv(src),
+                    Opcode::Cdv => Op::Cdv(src),
+                }
+            })
+            .collect();
+        Compiled {
+            ops,
+            registers: self.registers.file,
+            max_steps: self.program_raw.len().div_ceil(2).max(1) * COMPILED_PASSES,
+        }
+    }
+}
+
+impl Compiled {
+    /// Fail runs with [Fault::StepLimit] after `max_steps` instructions, for programs that may never halt
+    pub fn with_step_limit(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Run with `a` in register A, passing each output to `out`. Stops early (successfully) if `out` returns false.
+    fn exec<F: FnMut(i64) -> bool>(&self, a: i64, mut out: F) -> Result<(), Fault> {
+        let mut reg = self.registers;
+        reg[0] = a;
+        let load = |reg: &[i64; 3], src: Src| match src {
+            Src::Literal(v) => v,
+            Src::Register(r) => reg[r],
+        };
+        let mut ip = 0;
+        let mut steps = 0;
+        while let Some(op) = self.ops.get(ip) {
+            if steps == self.max_steps {
+                return Err(Fault::StepLimit { steps });
+            }
+            steps += 1;
+            ip += 2;
+            match *op {
+                Op::Adv(src) => reg[0] = divide_pow2(reg[0], load(&reg, src)),
+                Op::Bxl(v) => reg[1] ^= v,
+                Op::Bst(src) => reg[1] = modulo8(load(&reg, src)),
+                Op::Jnz(target) => {
+                    if reg[0] != 0 {
+                        ip = target;
+                    }
+                }
+                Op::Bxc => reg[1] ^= reg[2],
+                Op::Out(src) => {
+                    if !out(modulo8(load(&reg, src))) {
+                        return Ok(());
+                    }
+                }
+                Op::Bdv(src) => reg[1] = divide_pow2(reg[0], load(&reg, src)),
+                Op::Cdv(src) => reg[2] = divide_pow2(reg[0], load(&reg, src)),
+                Op::Reserved => return Err(Fault::ReservedOperand { ip: ip - 2 }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Run with `a` in register A, replacing the contents of `output` with the program's output
+    pub fn run_into(&self, a: i64, output: &mut Vec<i64>) -> Result<(), Fault> {
+        output.clear();
+        self.exec(a, |v| {
+            output.push(v);
+            true
+        })
+    }
+
+    /// The output of running with `a` in register A
+    pub fn run(&self, a: i64) -> Result<Vec<i64>, Fault> {
+        let mut output = Vec::new();
+        self.run_into(a, &mut output)?;
+        Ok(output)
+    }
+
+    /// Whether running with `a` in register A outputs exactly `target`, stopping at the first mismatch
+    pub fn outputs(&self, a: i64, target: &[i64]) -> bool {
+        let mut matched = 0;
+        let mut mismatch = false;
+        let result = self.exec(a, |v| {
+            if target.get(matched) == Some(&v) {
+                matched += 1;
+            } else {
+                mismatch = true;
+            }
+            !mismatch
+        });
+        result.is_ok() && !mismatch && matched == target.len()
+    }
+
+    /// The output (or fault) for each of `a_values`, evaluated in parallel
+    pub fn run_batch(&self, a_values: &[i64]) -> Vec<Result<Vec<i64>, Fault>> {
+        a_values.par_iter().map(|a| self.run(*a)).collect()
+    }
+
+    /// Those of `a_values` that make the program output exactly `target`, evaluated in parallel
+    pub fn batch_matching(&self, a_values: &[i64], target: &[i64]) -> Vec<i64> {
+        a_values
+            .par_iter()
+            .copied()
+            .filter(|a| self.outputs(*a, target))
+            .collect()
+    }
+}
+
 #[aoc(day17, part1)]
 pub fn part1(input: &str) -> String {
     let mut machine = parse(input);
@@ -242,33 +830,530 @@ pub fn part1(input: &str) -> String {
     machine.out_file.iter().map(|n| n.to_string()).join(",")
 }
 
-// The program must be of the correct form to be solvable
-pub fn solve(m: &mut Machine, guess: i64, i: usize) -> Option<i64> {
-    if i == m.program_raw.len() {
-        return Some(guess);
+/// Why [solve] or [solve_all] couldn't find an initial A for a program
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SolveError {
+    /// The target holds a value that no `out` instruction can produce
+    InvalidTarget { index: usize, value: i64 },
+    /// No initial A produces the target. At best the program produces its first `matched` values, after which every
+    /// A that got that far meets `dead_end`.
+    NoSolution { matched: usize, dead_end: DeadEnd },
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::InvalidTarget { index, value } => {
+                write!(f, "target value {} at index {} is not a 3-bit output", value, index)
+            }
+            SolveError::NoSolution { matched, dead_end } => write!(
+                f,
+                "no initial A produces the target, at most its first {} values are produced before {}",
+                matched, dead_end
+            ),
+        }
     }
-    let program_pos = m.program_raw.len() - 1 - i;
-    let goal_digit = m.program_raw[program_pos];
+}
 
-    for digit in 0..8 {
-        let local_guess = (digit << (program_pos * 3)) + guess;
-        m.reset();
-        m.registers.store(Register::A, local_guess);
-        m.run();
-        if m.out_file.len() == m.program_raw.len() && m.out_file[program_pos] == goal_digit {
-            if let Some(sol) = solve(m, local_guess, i + 1) {
-                return Some(sol);
+/// Why one branch of the [solve] search ended without a solution
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DeadEnd {
+    /// The `out` at `ip` can't produce the next target value for any A that reaches it
+    Mismatch { ip: usize },
+    /// The `out` at `ip` produces more values than the target has
+    ExtraOutput { ip: usize },
+    /// The program halts before producing the whole target
+    Halted,
+    /// The instruction at `ip` uses the reserved combo operand 7
+    ReservedOperand { ip: usize },
+    /// `steps` instructions ran without the branch halting, the program may loop forever
+    StepLimit { steps: usize },
+    /// The conditions the branch places on A can each be met, but not all at once
+    Infeasible,
+}
+
+impl Display for DeadEnd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadEnd::Mismatch { ip } => write!(f, "the out at ip {} produces a different value", ip),
+            DeadEnd::ExtraOutput { ip } => write!(f, "the out at ip {} produces too many values", ip),
+            DeadEnd::Halted => f.write_str("the program halts"),
+            DeadEnd::ReservedOperand { ip } => write!(f, "the reserved combo operand at ip {}", ip),
+            DeadEnd::StepLimit { steps } => write!(f, "{} steps without halting", steps),
+            DeadEnd::Infeasible => f.write_str("the conditions on A contradict each other"),
+        }
+    }
+}
+
+/// Instructions each branch of the [solve] search may execute before it's abandoned as an infinite loop
+const SOLVE_STEPS: usize = 10_000;
+
+/// One bit of a register as a function of the initial A: the XOR of the A bits in `vars`, inverted if `invert` is
+/// set. Every instruction except a shift by a register value acts linearly on these over GF(2), so they stay exact.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+struct Bit {
+    vars: u64,
+    invert: bool,
+}
+
+impl Bit {
+    const ZERO: Bit = Bit::constant(false);
+
+    const fn constant(value: bool) -> Self {
+        Self { vars: 0, invert: value }
+    }
+    fn var(i: u32) -> Self {
+        Self {
+            vars: 1 << i,
+            invert: false,
+        }
+    }
+    fn xor(self, other: Self) -> Self {
+        Self {
+            vars: self.vars ^ other.vars,
+            invert: self.invert ^ other.invert,
+        }
+    }
+    /// The bit's value, if it doesn't depend on A
+    fn value(self) -> Option<bool> {
+        (self.vars == 0).then_some(self.invert)
+    }
+}
+
+/// A register, least significant bit first
+type Word = [Bit; 64];
+
+fn word_constant(val: i64) -> Word {
+    std::array::from_fn(|i| Bit::constant(val >> i & 1 == 1))
+}
+
+fn word_xor(a: &Word, b: &Word) -> Word {
+    std::array::from_fn(|i| a[i].xor(b[i]))
+}
+
+/// [modulo8] of a word
+fn word_modulo8(w: &Word) -> Word {
+    std::array::from_fn(|i| if i < 3 { w[i] } else { Bit::ZERO })
+}
+
+/// [divide_pow2] of a word by a known shift, which only moves bits around
+fn word_divide_pow2(w: &Word, shift: i64) -> Word {
+    if shift >= 0 {
+        let shift = shift.min(63) as usize;
+        std::array::from_fn(|i| w[(i + shift).min(63)])
+    } else {
+        let shift = shift.unsigned_abs().min(64) as usize;
+        std::array::from_fn(|i| if i >= shift { w[i - shift] } else { Bit::ZERO })
+    }
+}
+
+/// What the initial A must satisfy for a branch of the [solve] search to be taken: linear equations over its bits,
+/// and groups of bits that can't all be zero
+#[derive(Debug, Clone)]
+struct Constraints {
+    /// Equations `bit = 0` in echelon form, each stored at the index of its highest variable
+    rows: [Option<Bit>; 64],
+    /// Each group needs at least one bit set
+    nonzero: Vec<Vec<Bit>>,
+}
+
+impl Constraints {
+    fn new() -> Self {
+        Self {
+            rows: [None; 64],
+            nonzero: Vec::new(),
+        }
+    }
+    /// `bit` rewritten without any variable the equations already determine. It's constant iff the equations fix
+    /// its value.
+    fn reduce(&self, mut bit: Bit) -> Bit {
+        let mut pending = bit.vars;
+        while pending != 0 {
+            let i = 63 - pending.leading_zeros();
+            if let Some(row) = self.rows[i as usize] {
+                bit = bit.xor(row);
+            }
+            pending = bit.vars & ((1 << i) - 1);
+        }
+        bit
+    }
+    /// Require `bit` to equal `value`, returning false if that contradicts the constraints
+    fn require(&mut self, bit: Bit, value: bool) -> bool {
+        let row = self.reduce(bit.xor(Bit::constant(value)));
+        match row.value() {
+            Some(contradiction) => !contradiction,
+            None => {
+                self.rows[63 - row.vars.leading_zeros() as usize] = Some(row);
+                self.check_nonzero()
+            }
+        }
+    }
+    /// Require at least one of `bits` to be set, returning false if that contradicts the constraints
+    fn require_nonzero(&mut self, bits: Vec<Bit>) -> bool {
+        // a loop that jumps on the same A each time round would otherwise pile up copies
+        if self
+            .nonzero
+            .iter()
+            .any(|group| group.iter().all(|bit| bits.contains(bit)))
+        {
+            return true;
+        }
+        self.nonzero.push(bits);
+        self.check_nonzero()
+    }
+    /// Whether each group of [Constraints::nonzero] can still be met on its own. Together they might not be, which
+    /// [Constraints::each_solution] settles by fixing every bit.
+    fn check_nonzero(&mut self) -> bool {
+        let mut i = 0;
+        while i < self.nonzero.len() {
+            // the first bit of each group witnesses that it can be met, until the equations fix it
+            let met = loop {
+                let Some(&first) = self.nonzero[i].first() else {
+                    return false;
+                };
+                match self.reduce(first).value() {
+                    None => break false,
+                    Some(true) => break true,
+                    Some(false) => {
+                        self.nonzero[i].swap_remove(0);
+                    }
+                }
+            };
+            if met {
+                self.nonzero.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        true
+    }
+    /// The value of `word`, if the constraints fix it
+    fn concrete(&self, word: &Word) -> Option<i64> {
+        word.iter().enumerate().try_fold(0, |val, (i, bit)| {
+            self.reduce(*bit).value().map(|set| val | (set as i64) << i)
+        })
+    }
+    /// Pass each A up to `bound` meeting the constraints to `found` in ascending order, stopping early (and returning
+    /// false) when it returns false. Bits are fixed from the most significant down, trying 0 first.
+    fn each_solution<F: FnMut(i64) -> bool>(&self, bound: i64, found: &mut F) -> bool {
+        self.assign(63, 0, bound, found)
+    }
+    fn assign<F: FnMut(i64) -> bool>(&self, free_bits: u32, prefix: i64, bound: i64, found: &mut F) -> bool {
+        let Some(bit) = free_bits.checked_sub(1) else {
+            return found(prefix);
+        };
+        for value in [false, true] {
+            let a = prefix | (value as i64) << bit;
+            if a > bound {
+                break;
+            }
+            let mut next = self.clone();
+            if next.require(Bit::var(bit), value) && !next.assign(bit, a, bound, found) {
+                return false;
+            }
+        }
+        true
+    }
+    fn smallest(&self, bound: i64) -> Option<i64> {
+        let mut smallest = None;
+        self.each_solution(bound, &mut |a| {
+            smallest = Some(a);
+            false
+        });
+        smallest
+    }
+    /// Every way a shift by the symbolic `shift` can go, as the concrete shift to apply and the constraints under
+    /// which it applies. Shifts of 64 or more all act alike, as do shifts of -65 or less, so each of those ranges is
+    /// one case.
+    fn shift_cases(&self, shift: &Word) -> Vec<(i64, Constraints)> {
+        let reduced = shift.map(|bit| self.reduce(bit));
+        let mut cases = Vec::new();
+        for val in -64..=63 {
+            let want = word_constant(val);
+            let clash = reduced
+                .iter()
+                .zip(&want)
+                .any(|(bit, want)| bit.value().is_some_and(|set| set != want.invert));
+            if clash {
+                continue;
+            }
+            let mut constraints = self.clone();
+            if reduced
+                .iter()
+                .zip(&want)
+                .all(|(bit, want)| constraints.require(*bit, want.invert))
+            {
+                cases.push((val, constraints));
+            }
+        }
+        // the rest have a bit between 6 and 62 that differs from the sign
+        for (val, negative) in [(64, false), (-65, true)] {
+            let mut constraints = self.clone();
+            let differs = reduced[6..63]
+                .iter()
+                .map(|bit| bit.xor(Bit::constant(negative)))
+                .collect();
+            if constraints.require(reduced[63], negative) && constraints.require_nonzero(differs) {
+                cases.push((val, constraints));
+            }
+        }
+        cases
+    }
+}
+
+/// One path through the program, with registers as functions of the initial A
+#[derive(Debug, Clone)]
+struct Branch {
+    registers: [Word; 3],
+    ip: usize,
+    outputs: usize,
+    steps: usize,
+    constraints: Constraints,
+}
+
+/// Symbolic execution of a program over every initial A at once, forking where a jump or a register-valued shift
+/// depends on A and abandoning a branch as soon as its output contradicts the target
+struct Solver<'a> {
+    program: &'a [i64],
+    target: &'a [i64],
+    /// The dead end of the branch that matched the most of the target
+    deepest: Option<(usize, DeadEnd)>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(m: &'a Machine, target: &'a [i64]) -> Result<Self, SolveError> {
+        if let Some((index, value)) = target.iter().enumerate().find(|(_, v)| !(0..8).contains(*v)) {
+            return Err(SolveError::InvalidTarget { index, value: *value });
+        }
+        Ok(Self {
+            program: &m.program_raw,
+            target,
+            deepest: None,
+        })
+    }
+    fn dead_end(&mut self, matched: usize, dead_end: DeadEnd) {
+        if self.deepest.is_none_or(|(deepest, _)| matched > deepest) {
+            self.deepest = Some((matched, dead_end));
+        }
+    }
+    fn error(&self) -> SolveError {
+        let (matched, dead_end) = self.deepest.unwrap_or((0, DeadEnd::Infeasible));
+        SolveError::NoSolution { matched, dead_end }
+    }
+
+    /// Explore every branch starting from the registers of `m` with A unknown, passing the constraints of each
+    /// branch that outputs exactly the target to `complete`, which returns whether any A meets them
+    fn search<F: FnMut(&Constraints) -> bool>(&mut self, m: &Machine, mut complete: F) {
+        let mut a: Word = std::array::from_fn(|i| Bit::var(i as u32));
+        // A is non-negative
+        a[63] = Bit::ZERO;
+        let mut stack = vec![Branch {
+            registers: [
+                a,
+                word_constant(m.register(Register::B)),
+                word_constant(m.register(Register::C)),
+            ],
+            ip: 0,
+            outputs: 0,
+            steps: 0,
+            constraints: Constraints::new(),
+        }];
+        while let Some(branch) = stack.pop() {
+            self.run(branch, &mut stack, &mut complete);
+        }
+    }
+
+    /// Run `branch` until it halts or fails, or forks into branches that are pushed onto `stack`
+    fn run<F: FnMut(&Constraints) -> bool>(&mut self, mut branch: Branch, stack: &mut Vec<Branch>, complete: &mut F) {
+        loop {
+            let ip = branch.ip;
+            let Some(&[opcode, operand]) = self.program.get(ip..ip + 2) else {
+                if branch.outputs < self.target.len() {
+                    self.dead_end(branch.outputs, DeadEnd::Halted);
+                } else if !complete(&branch.constraints) {
+                    self.dead_end(branch.outputs, DeadEnd::Infeasible);
+                }
+                return;
+            };
+            if branch.steps == SOLVE_STEPS {
+                self.dead_end(branch.outputs, DeadEnd::StepLimit { steps: SOLVE_STEPS });
+                return;
             }
+            branch.steps += 1;
+            branch.ip += 2;
+
+            let inst = Instruction::decode(opcode, operand);
+            let value = match inst.operand {
+                Operand::Literal(v) => word_constant(v),
+                Operand::Load(reg) => branch.registers[reg as usize],
+                Operand::Ignore(_) => word_constant(0),
+                Operand::Reserved => {
+                    self.dead_end(branch.outputs, DeadEnd::ReservedOperand { ip });
+                    return;
+                }
+            };
+            let [a, b, c] = &branch.registers;
+            match inst.opcode {
+                Opcode::Adv | Opcode::Bdv | Opcode::Cdv => {
+                    let dest = match inst.opcode {
+                        Opcode::Adv => Register::A,
+                        Opcode::Bdv => Register::B,
+                        _ => Register::C,
+                    } as usize;
+                    let a = *a;
+                    if let Some(shift) = branch.constraints.concrete(&value) {
+                        branch.registers[dest] = word_divide_pow2(&a, shift);
+                        continue;
+                    }
+                    for (shift, constraints) in branch.constraints.shift_cases(&value).into_iter().rev() {
+                        let mut fork = branch.clone();
+                        fork.registers[dest] = word_divide_pow2(&a, shift);
+                        fork.constraints = constraints;
+                        stack.push(fork);
+                    }
+                    return;
+                }
+                Opcode::Bxl => branch.registers[1] = word_xor(b, &value),
+                Opcode::Bst => branch.registers[1] = word_modulo8(&value),
+                Opcode::Bxc => branch.registers[1] = word_xor(b, c),
+                Opcode::Jnz => {
+                    let bits = a.map(|bit| branch.constraints.reduce(bit));
+                    if bits.iter().any(|bit| bit.value() == Some(true)) {
+                        branch.ip = operand as usize;
+                    } else if bits.iter().any(|bit| bit.value().is_none()) {
+                        let mut zero = branch.clone();
+                        if bits.iter().all(|bit| zero.constraints.require(*bit, false)) {
+                            stack.push(zero);
+                        }
+                        let unknown = bits.into_iter().filter(|bit| bit.value().is_none()).collect();
+                        if !branch.constraints.require_nonzero(unknown) {
+                            return;
+                        }
+                        branch.ip = operand as usize;
+                    }
+                }
+                Opcode::Out => {
+                    let index = branch.outputs;
+                    let Some(&expected) = self.target.get(index) else {
+                        self.dead_end(index, DeadEnd::ExtraOutput { ip });
+                        return;
+                    };
+                    let out = word_modulo8(&value);
+                    if !(0..3).all(|i| branch.constraints.require(out[i], expected >> i & 1 == 1)) {
+                        self.dead_end(index, DeadEnd::Mismatch { ip });
+                        return;
+                    }
+                    branch.outputs += 1;
+                }
+            }
+        }
+    }
+}
+
+/// The smallest initial value of register A that makes `m` output exactly `target`, keeping its B and C. Any
+/// program is accepted: the search runs it symbolically, tracking each register bit as an XOR of bits of A, so a
+/// failure is reported as how far the best attempt got and why it stopped.
+pub fn solve(m: &Machine, target: &[i64]) -> Result<i64, SolveError> {
+    let mut solver = Solver::new(m, target)?;
+    let mut best: Option<i64> = None;
+    solver.search(m, |constraints| match constraints.smallest(i64::MAX) {
+        Some(a) => {
+            best = Some(best.map_or(a, |best| best.min(a)));
+            true
+        }
+        None => false,
+    });
+    best.ok_or_else(|| solver.error())
+}
+
+/// Every initial value of register A up to `bound` that makes `m` output exactly `target`, in ascending order. Fails
+/// only if no A at all produces the target.
+pub fn solve_all(m: &Machine, target: &[i64], bound: i64) -> Result<Vec<i64>, SolveError> {
+    let mut solver = Solver::new(m, target)?;
+    let mut solutions = Vec::new();
+    let mut any = false;
+    solver.search(m, |constraints| {
+        if constraints.smallest(i64::MAX).is_none() {
+            return false;
         }
+        any = true;
+        constraints.each_solution(bound, &mut |a| {
+            solutions.push(a);
+            true
+        });
+        true
+    });
+    if !any {
+        return Err(solver.error());
     }
-    None
+    solutions.sort();
+    Ok(solutions)
 }
 
 #[aoc(day17, part2)]
 pub fn part2(input: &str) -> i64 {
-    let mut machine = parse(input);
+    let machine = parse(input);
+    solve(&machine, &machine.program_raw).unwrap_or_else(|e| panic!("program can't output itself: {}", e))
+}
+
+#[aoc(day17, part1, Compiled)]
+pub fn part1_compiled(input: &str) -> String {
+    let machine = parse(input);
+    let output = machine.compile().run(machine.register(Register::A)).unwrap();
+    output.iter().map(|n| n.to_string()).join(",")
+}
+
+/// Part 2 through [solve_compiled] where the program allows it, falling back to [solve]
+#[aoc(day17, part2, Compiled)]
+pub fn part2_compiled(input: &str) -> i64 {
+    let machine = parse(input);
+    solve_compiled(&machine, &machine.program_raw).unwrap_or_else(|| {
+        solve(&machine, &machine.program_raw).unwrap_or_else(|e| panic!("program can't output itself: {}", e))
+    })
+}
 
-    solve(&mut machine, 0, 0).expect("expected a solution")
+/// The smallest initial A that makes `m` output `target`, found breadth-first for programs shaped like the puzzle's:
+/// a single loop ending in `jnz 0`, with one `adv` by a literal as its only change to A, outputting the same number
+/// of values each time round. Every candidate for the last `n` passes' bits is extended by every possible chunk and
+/// the whole batch is checked against the last `n + 1` passes' output in parallel.
+///
+/// Returns `None` if the program isn't shaped like that or no candidate matches, in which case [solve] still
+/// applies.
+pub fn solve_compiled(m: &Machine, target: &[i64]) -> Option<i64> {
+    let program = m.disassembly();
+    let (jumps, body): (Vec<_>, Vec<_>) = program.iter().partition(|(_, inst)| inst.opcode == Opcode::Jnz);
+    let [(jump_ip, jump)] = jumps[..] else {
+        return None;
+    };
+    if jump.operand != Operand::Literal(0) || jump_ip + 2 != m.program_raw.len() {
+        return None;
+    }
+    let writes_a = body.iter().filter(|(_, inst)| inst.opcode == Opcode::Adv).collect_vec();
+    let shift = match writes_a[..] {
+        [(
+            _,
+            Instruction {
+                operand: Operand::Literal(shift @ 1..),
+                ..
+            },
+        )] => *shift,
+        _ => return None,
+    };
+    let outputs = body.iter().filter(|(_, inst)| inst.opcode == Opcode::Out).count();
+    if outputs == 0 || target.is_empty() || !target.len().is_multiple_of(outputs) {
+        return None;
+    }
+
+    let compiled = m.compile();
+    let mut candidates = vec![0];
+    for suffix in (outputs..=target.len()).step_by(outputs) {
+        let extended = candidates
+            .iter()
+            .flat_map(|prefix| (0..1 << shift).map(move |bits| (prefix << shift) | bits))
+            .collect_vec();
+        candidates = compiled.batch_matching(&extended, &target[target.len() - suffix..]);
+    }
+    candidates.into_iter().min()
 }
 
 #[cfg(test)]
@@ -295,4 +1380,392 @@ Program: 0,3,5,4,3,0";
     fn part2_example() {
         assert_eq!(part2(EXAMPLE2), 117440);
     }
+
+    #[test]
+    fn disassemble() {
+        let machine = parse(EXAMPLE1);
+        assert_eq!(machine.disassemble(), "  0: adv 1\n  2: out A\n  4: jnz 0\n");
+        let machine = parse("Program: 2,6,4,7,1,7,0");
+        assert_eq!(
+            machine
+                .disassembly()
+                .iter()
+                .map(|(_, inst)| inst.to_string())
+                .collect_vec(),
+            ["bst C", "bxc 7", "bxl 7"]
+        );
+        assert_eq!(parse("Program: 5,7").disassemble(), "  0: out <reserved>\n");
+    }
+
+    #[test]
+    fn trace() {
+        let mut machine = parse("Register C: 9\n\nProgram: 2,6");
+        let trace = machine.trace(10).unwrap();
+        assert_eq!(
+            trace,
+            [TraceEntry {
+                ip: 0,
+                instruction: Instruction {
+                    opcode: Opcode::Bst,
+                    operand: Operand::Load(Register::C)
+                },
+                registers: [0, 1, 9],
+                output: None
+            }]
+        );
+        assert_eq!(trace[0].to_string(), "  0: bst C  A=0 B=1 C=9");
+
+        let mut machine = parse(EXAMPLE1);
+        let trace = machine.trace(100).unwrap();
+        assert_eq!(trace.len(), 30);
+        assert_eq!(trace.iter().filter_map(|e| e.output).collect_vec(), machine.output());
+        assert_eq!(trace[1].to_string(), "  2: out A  A=364 B=0 C=0 -> 4");
+    }
+
+    #[test]
+    fn debugger() {
+        let mut machine = parse(EXAMPLE1);
+        assert_eq!(machine.run_until_output(100), Ok(Stop::Output(4)));
+        assert_eq!(machine.ip(), 4);
+        assert_eq!(machine.run_until_ip(2, 100), Ok(Stop::Breakpoint(2)));
+        assert_eq!(machine.register(Register::A), 182);
+        assert_eq!(machine.run_until_ip(2, 100), Ok(Stop::Breakpoint(2)));
+        assert_eq!(machine.output(), [4, 6]);
+        assert_eq!(machine.run_until_breakpoints(&[], 100), Ok(Stop::Halted));
+        assert_eq!(machine.output().len(), 10);
+        assert_eq!(machine.step(), Ok(None));
+
+        // jnz 0 with A never reaching 0
+        let mut machine = parse("Register A: 1\n\nProgram: 3,0");
+        assert_eq!(machine.run_until_output(50), Err(Fault::StepLimit { steps: 50 }));
+
+        let mut machine = parse("Register A: 1\n\nProgram: 1,1,5,7");
+        assert_eq!(machine.run_until_output(50), Err(Fault::ReservedOperand { ip: 2 }));
+        assert_eq!(machine.register(Register::B), 1);
+    }
+
+    #[test]
+    fn jump_addresses() {
+        // jnz 2 skips to the second instruction, not the third
+        let mut machine = parse("Register A: 1\nRegister B: 3\n\nProgram: 3,2,5,5,5,4");
+        machine.run();
+        assert_eq!(machine.output(), [3, 1]);
+        // a jump to an odd address reads the program misaligned, here as `out B`
+        let mut machine = parse("Register A: 1\n\nProgram: 3,3,0,5,5,0");
+        machine.set_register(Register::B, 6);
+        machine.run();
+        assert_eq!(machine.output(), [6]);
+    }
+
+    #[test]
+    fn solve_any_shift() {
+        let machine = parse(EXAMPLE1);
+        let target = [4, 6, 3, 5, 6, 3, 5, 2, 1, 0];
+        // `adv 1` then `out A` never sees the lowest bit of A
+        assert_eq!(solve(&machine, &target), Ok(728));
+        assert_eq!(solve_all(&machine, &target, 1000), Ok(vec![728, 729]));
+        assert_eq!(solve_all(&machine, &target, 728), Ok(vec![728]));
+        assert_eq!(solve(&parse(EXAMPLE2), &[5, 0]), Ok(40));
+    }
+
+    #[test]
+    fn solve_matches_brute_force() {
+        // state carried between iterations, register-valued shifts, inner jumps and negative registers
+        for source in [
+            "bxl 1\nadv 3\nout B\njnz 0",
+            "adv A\nout A\njnz 0",
+            "bst A\ncdv B\nbxc\nout C\nadv 2\njnz 0",
+            "start:\nadv 1\njnz odd\nout 3\nodd:\nout A\nadv 1\njnz start",
+            "C = -3\nbst A\nbxc\nout B\nbdv C\nadv 3\njnz 0",
+            "C = -8\nbst A\nbxc\nadv B\nout A\nadv 6\njnz 0",
+        ] {
+            let assembly = assemble(source).unwrap();
+            let machine = parse(&assembly.to_string());
+            let outputs = (0..1 << 10)
+                .map(|a| {
+                    let mut m = machine.clone();
+                    m.set_register(Register::A, a);
+                    m.trace(1000).ok().map(|_| m.output().to_vec())
+                })
+                .collect_vec();
+            for a in [0, 1, 37, 500, 1000] {
+                let Some(target) = &outputs[a] else {
+                    continue;
+                };
+                let matching = (0..outputs.len() as i64)
+                    .filter(|&a| outputs[a as usize].as_ref() == Some(target))
+                    .collect_vec();
+                assert_eq!(solve(&machine, target), Ok(matching[0]), "A={} in {:?}", a, source);
+                assert_eq!(
+                    solve_all(&machine, target, 1023),
+                    Ok(matching),
+                    "A={} in {:?}",
+                    a,
+                    source
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn solve_puzzle_shape() {
+        let machine = parse("Program: 2,4,1,1,7,5,1,5,4,0,0,3,5,5,3,0");
+        let a = 0o3141_5726_5350_7713;
+        let mut m = machine.clone();
+        m.set_register(Register::A, a);
+        m.run();
+        let target = m.output().to_vec();
+        let solved = solve(&machine, &target).unwrap();
+        assert!(solved <= a);
+        let mut m = machine.clone();
+        m.set_register(Register::A, solved);
+        m.run();
+        assert_eq!(m.output(), target);
+        assert!(solve_all(&machine, &target, a).unwrap().contains(&a));
+    }
+
+    #[test]
+    fn not_invertible() {
+        let err = |program: &str, target: &[i64]| solve(&parse(&format!("Program: {}", program)), target).unwrap_err();
+        assert_eq!(
+            err("0,3,5,4,3,0", &[8]),
+            SolveError::InvalidTarget { index: 0, value: 8 }
+        );
+        // the last iteration always outputs 0, and every earlier one outputs the next iteration's nonzero A
+        assert_eq!(
+            err("0,3,5,4,3,0", &[0, 0, 0]),
+            SolveError::NoSolution {
+                matched: 3,
+                dead_end: DeadEnd::ExtraOutput { ip: 2 }
+            }
+        );
+        assert_eq!(
+            err("0,3,5,4,3,0", &[0, 0, 0]).to_string(),
+            "no initial A produces the target, at most its first 3 values are produced before the out at ip 2 \
+             produces too many values"
+        );
+        let dead_end = |program: &str, target: &[i64]| match err(program, target) {
+            SolveError::NoSolution { matched, dead_end } => (matched, dead_end),
+            err => panic!("unexpected {:?}", err),
+        };
+        assert_eq!(dead_end("5,1", &[2]), (0, DeadEnd::Mismatch { ip: 0 }));
+        assert_eq!(dead_end("5,4", &[1, 2]), (1, DeadEnd::Halted));
+        assert_eq!(dead_end("1,1,5,7", &[0]), (0, DeadEnd::ReservedOperand { ip: 2 }));
+        assert_eq!(dead_end("3,0", &[1]), (0, DeadEnd::StepLimit { steps: SOLVE_STEPS }));
+        assert_eq!(
+            solve_all(&parse("Program: 5,1"), &[2], 100).unwrap_err(),
+            err("5,1", &[2])
+        );
+    }
+
+    #[test]
+    fn assemble_round_trip() {
+        let source = "; the part 2 example, which outputs itself
+A = 117440
+
+start:
+    adv 3     ; drop the low octal digit
+    out A
+    jnz start
+";
+        let assembly = assemble(source).unwrap();
+        assert_eq!(assembly.registers, [117440, 0, 0]);
+        assert_eq!(assembly.program, [0, 3, 5, 4, 3, 0]);
+        assert_eq!(
+            assembly.to_string(),
+            "Register A: 117440\nRegister B: 0\nRegister C: 0\n\nProgram: 0,3,5,4,3,0"
+        );
+        let mut machine = parse(&assembly.to_string());
+        assert_eq!(
+            machine
+                .disassembly()
+                .iter()
+                .map(|(_, inst)| inst.to_string())
+                .join("\n"),
+            "adv 3\nout A\njnz 0"
+        );
+        machine.run();
+        assert_eq!(machine.output(), assembly.program);
+
+        // disassembled mnemonics assemble back to the same program, including bxc's ignored operand
+        let program = "Program: 2,4,1,3,7,5,4,3,1,3,0,3,5,5,4,0,3,0";
+        let listing = parse(program)
+            .disassembly()
+            .iter()
+            .map(|(_, inst)| inst.to_string())
+            .join("\n");
+        assert_eq!(assemble(&listing).unwrap().to_string().lines().last(), Some(program));
+
+        let assembly = assemble("B = 3\nskip: jnz past ; forward reference\n.data 5, 5\npast: out B").unwrap();
+        assert_eq!(assembly.program, [3, 4, 5, 5, 5, 5]);
+
+        // negative register initialisers survive the trip through the puzzle format
+        let assembly = assemble("A = -5\nB = 3\nC = -9223372036854775808\nbxc\nout B").unwrap();
+        let machine = parse(&assembly.to_string());
+        assert_eq!(
+            [Register::A, Register::B, Register::C].map(|r| machine.register(r)),
+            assembly.registers
+        );
+        assert_eq!(machine.disassemble(), "  0: bxc\n  2: out B\n");
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let err = |source: &str| assemble(source).unwrap_err();
+        let kind = |source: &str| err(source).kind;
+        assert_eq!(
+            err("adv 1\n\nout 7"),
+            AsmError {
+                line: 3,
+                kind: AsmErrorKind::ReservedOperand
+            }
+        );
+        assert_eq!(err("adv 1\n\nout 7").to_string(), "line 3: combo operand 7 is reserved");
+        assert_eq!(kind("mul 3"), AsmErrorKind::UnknownMnemonic("mul".to_string()));
+        assert_eq!(kind("adv"), AsmErrorKind::MissingOperand);
+        assert_eq!(kind("adv 1 2"), AsmErrorKind::TrailingInput("2".to_string()));
+        assert_eq!(kind("bxl A"), AsmErrorKind::InvalidOperand("A".to_string()));
+        assert_eq!(kind("bxl 8"), AsmErrorKind::InvalidOperand("8".to_string()));
+        assert_eq!(kind("bxc A"), AsmErrorKind::InvalidOperand("A".to_string()));
+        assert_eq!(kind("out D"), AsmErrorKind::InvalidOperand("D".to_string()));
+        assert_eq!(kind("D = 1"), AsmErrorKind::InvalidOperand("D".to_string()));
+        assert_eq!(kind("jnz nowhere"), AsmErrorKind::UnknownLabel("nowhere".to_string()));
+        assert_eq!(
+            kind("a: out A\na: out B"),
+            AsmErrorKind::DuplicateLabel("a".to_string())
+        );
+        assert_eq!(
+            err("out A\n.data 1, 2\nout A\nout A\nend: jnz end"),
+            AsmError {
+                line: 5,
+                kind: AsmErrorKind::LabelOutOfRange {
+                    label: "end".to_string(),
+                    address: 8
+                }
+            }
+        );
+        assert_eq!(
+            err("out A\n.data 1\n; trailing comment"),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::OddLength
+            }
+        );
+    }
+
+    #[test]
+    fn compiled_matches_interpreter() {
+        assert_eq!(part1_compiled(EXAMPLE1), part1(EXAMPLE1));
+        assert_eq!(part2_compiled(EXAMPLE2), part2(EXAMPLE2));
+
+        for program in [
+            "Register B: 5\nRegister C: 3\n\nProgram: 2,4,1,3,7,5,4,1,1,3,0,3,5,5,3,0",
+            "Register A: 1\nRegister B: 3\n\nProgram: 3,2,5,5,5,4",
+            "Program: 3,3,0,5,5,0",
+        ] {
+            let machine = parse(program);
+            let compiled = machine.compile();
+            let a_values = (0..2000).collect_vec();
+            for (a, result) in a_values.iter().zip(compiled.run_batch(&a_values)) {
+                let mut interpreted = machine.clone();
+                interpreted.set_register(Register::A, *a);
+                interpreted.run();
+                assert_eq!(result.unwrap(), interpreted.output(), "A={} in {}", a, program);
+            }
+        }
+    }
+
+    #[test]
+    fn compiled_matches_interpreter_edge_cases() {
+        // register-valued shifts far wider than a register, negative registers and negative shifts
+        for source in [
+            "A = -5\nadv 1\nout A\nbst A\nout B\njnz 0",
+            "B = 100\nC = 63\nbdv B\nout B\ncdv C\nout C\nadv B\nout A",
+            "B = -7\nC = -1\nbxc\nout B\nadv C\nout A\ncdv B\nout C\nbdv C\nout B",
+            "C = -64\nadv C\nout A\nbxl 7\nbst A\nout B",
+            "C = -3\nbdv C\nbxc\nout B\nadv 3\njnz 0",
+        ] {
+            let assembly = assemble(source).unwrap();
+            let machine = parse(&assembly.to_string());
+            let compiled = machine.compile().with_step_limit(1000);
+            for a in [0, 1, -1, -5, 7, 1 << 40, i64::MAX, i64::MIN, -(1 << 62)] {
+                let mut interpreted = machine.clone();
+                interpreted.set_register(Register::A, a);
+                let expected = interpreted.trace(1000).map(|_| interpreted.output().to_vec());
+                assert_eq!(compiled.run(a), expected, "A={} in {:?}", a, source);
+                if let Ok(out) = compiled.run(a) {
+                    assert!(out.iter().all(|v| (0..8).contains(v)));
+                }
+            }
+        }
+        assert_eq!(divide_pow2(-5, 1), -3);
+        assert_eq!(divide_pow2(-5, 100), -1);
+        assert_eq!(divide_pow2(5, 64), 0);
+        assert_eq!(divide_pow2(5, -2), 20);
+        assert_eq!(divide_pow2(5, -64), 0);
+        assert_eq!(divide_pow2(5, i64::MIN), 0);
+        assert_eq!(modulo8(-3), 5);
+    }
+
+    #[test]
+    fn solve_compiled_shapes() {
+        let machine = parse(EXAMPLE2);
+        assert_eq!(solve_compiled(&machine, &machine.program_raw), Some(117440));
+        let shaped = |program: &str, target: &[i64]| solve_compiled(&parse(&format!("Program: {}", program)), target);
+        // no out, two outputs per pass against an odd-length target, a second write to A, an inner jump
+        assert_eq!(shaped("0,3,3,0", &[0]), None);
+        assert_eq!(shaped("0,3,5,4,5,4,3,0", &[1, 2, 3]), None);
+        assert_eq!(shaped("0,3,0,1,5,4,3,0", &[0]), None);
+        assert_eq!(shaped("0,3,3,6,5,4,3,0", &[0]), None);
+        // falls back to the general solver
+        assert_eq!(
+            part2_compiled("Program: 0,3,0,1,5,4,3,0"),
+            part2("Program: 0,3,0,1,5,4,3,0")
+        );
+    }
+
+    #[test]
+    fn compiled_step_limit() {
+        // jnz 0 spins forever once A is nonzero
+        let compiled = parse("Program: 5,4,3,0").compile();
+        assert_eq!(compiled.run(0), Ok(vec![0]));
+        assert_eq!(
+            compiled.run(1),
+            Err(Fault::StepLimit {
+                steps: 2 * COMPILED_PASSES
+            })
+        );
+        assert_eq!(
+            compiled.run_batch(&[0, 1])[1],
+            Err(Fault::StepLimit {
+                steps: 2 * COMPILED_PASSES
+            })
+        );
+        assert_eq!(compiled.batch_matching(&[0, 1, 2], &[0]), [0]);
+        assert_eq!(compiled.with_step_limit(10).run(1), Err(Fault::StepLimit { steps: 10 }));
+    }
+
+    #[test]
+    fn compiled_runs() {
+        let compiled = parse(EXAMPLE2).compile();
+        let mut output = vec![9, 9];
+        compiled.run_into(117440, &mut output).unwrap();
+        assert_eq!(output, [0, 3, 5, 4, 3, 0]);
+        assert!(compiled.outputs(117440, &[0, 3, 5, 4, 3, 0]));
+        assert!(!compiled.outputs(117440, &[0, 3, 5, 4, 3, 1]));
+        assert!(!compiled.outputs(117440, &[0, 3, 5, 4, 3]));
+        assert!(!compiled.outputs(117440, &[0, 3, 5, 4, 3, 0, 0]));
+        assert_eq!(
+            compiled.batch_matching(&(0..1 << 12).collect_vec(), &[4, 3, 0]),
+            [224, 225, 226, 227, 228, 229, 230, 231]
+        );
+
+        let looping = parse("Program: 3,0").compile().with_step_limit(100);
+        assert_eq!(looping.run(0), Ok(vec![]));
+        assert_eq!(looping.run(1), Err(Fault::StepLimit { steps: 100 }));
+        assert_eq!(
+            parse("Program: 1,1,5,7").compile().run(0),
+            Err(Fault::ReservedOperand { ip: 2 })
+        );
+    }
 }
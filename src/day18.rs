@@ -1,10 +1,9 @@
 use aoc_runner_derive::aoc;
-use grid::Grid;
-use itertools::Itertools;
-use std::{
-    cmp::Reverse,
-    collections::{BinaryHeap, VecDeque},
+use grid::{
+    search::{self, LengthPath, NoopTrack, PathTrack},
+    DisjointSet, Grid,
 };
+use itertools::Itertools;
 
 #[derive(Clone)]
 struct MemoryMap {
@@ -12,44 +11,6 @@ struct MemoryMap {
     byte_stream: Vec<(i64, i64)>,
 }
 
-trait PathTrack {
-    const DOES_WORK: bool = true;
-    fn new() -> Self;
-    fn push(&mut self, pos: (i64, i64));
-    fn finalize(&mut self) {}
-}
-
-struct LengthPath(usize);
-impl PathTrack for LengthPath {
-    fn new() -> Self {
-        LengthPath(0)
-    }
-    fn push(&mut self, _: (i64, i64)) {
-        self.0 += 1
-    }
-}
-
-impl PathTrack for Vec<(i64, i64)> {
-    fn new() -> Self {
-        Vec::new()
-    }
-    fn push(&mut self, pos: (i64, i64)) {
-        self.push(pos);
-    }
-    fn finalize(&mut self) {
-        self.reverse();
-    }
-}
-
-struct NoopTrack {}
-impl PathTrack for NoopTrack {
-    const DOES_WORK: bool = false;
-    fn new() -> Self {
-        Self {}
-    }
-    fn push(&mut self, _: (i64, i64)) {}
-}
-
 impl MemoryMap {
     fn from_str(input: &str, width: usize, height: usize) -> Self {
         let map = Grid::with_shape(width, height, true);
@@ -76,106 +37,28 @@ impl MemoryMap {
         }
     }
 
-    fn valid_moves<'a>(&'a self, pos: &'a (i64, i64)) -> impl Iterator<Item = (i64, i64)> + 'a {
-        ([(0, 1), (1, 0), (0, -1), (-1, 0)])
-            .iter()
-            .filter(|ofs| self.map.get(&(pos.0 + ofs.0, pos.1 + ofs.1)).is_some_and(|v| *v))
-            .map(|ofs| (pos.0 + ofs.0, pos.1 + ofs.1))
+    fn valid_moves(&self, pos: (i64, i64)) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.map
+            .neighbors4(&pos)
+            .filter(|(_, v)| **v)
+            .map(|(pos, _)| pos.into())
     }
 
-    fn bfs<T: PathTrack>(&self, start: (i64, i64)) -> Option<T> {
-        let goal = (self.map.width() as i64 - 1, self.map.height() as i64 - 1);
-
-        let mut visited = self.map.same_shape(false);
-        let mut prev = self.map.same_shape((i64::MAX, i64::MAX));
-        let mut queue = VecDeque::new();
-
-        visited.set(&start, true);
-        queue.push_back((0, start));
-
-        while let Some((depth, pos)) = queue.pop_front() {
-            if pos == goal {
-                if T::DOES_WORK {
-                    let mut visited_pos = goal;
-                    let mut path = T::new();
-                    path.push(pos);
-                    while let Some(next) = prev.get(&visited_pos) {
-                        visited_pos = *next;
-                        path.push(*next);
-                        if *next == start {
-                            path.finalize();
-                            return Some(path);
-                        }
-                    }
-                } else {
-                    return Some(T::new());
-                }
-            }
-
-            // if visited.get(&pos).is_some_and(|v| *v) {
-            //     continue;
-            // }
-
-            let moves = self.valid_moves(&pos);
-            for new_pos in moves {
-                if visited.get(&new_pos).is_none_or(|v| !v) {
-                    visited.set(&new_pos, true);
-                    if T::DOES_WORK {
-                        prev.set(&new_pos, pos);
-                    }
-                    queue.push_back((depth + 1, new_pos));
-                }
-            }
+    fn join_free_neighbours(&self, sets: &mut DisjointSet, pos: (i64, i64)) {
+        for (neighbour, _) in self.map.neighbors4(&pos).filter(|(_, free)| **free) {
+            sets.union(self.map.pos(&pos) as usize, self.map.pos(&neighbour) as usize);
         }
-        None
     }
 
-    #[allow(dead_code)] // will be moved to Grid at some point
-    fn dijkstra<T: PathTrack>(&self, start: (i64, i64)) -> Option<T> {
+    fn bfs<T: PathTrack<(i64, i64)>>(&self, start: (i64, i64)) -> Option<T> {
         let goal = (self.map.width() as i64 - 1, self.map.height() as i64 - 1);
-
-        let mut costs = self.map.same_shape(i64::MAX);
-        let mut prev = self.map.same_shape((i64::MAX, i64::MAX));
-        let mut queue = BinaryHeap::new();
-
-        costs.set(&start, 0);
-        queue.push((Reverse(0), start));
-
-        while let Some((cost, pos)) = queue.pop() {
-            if pos == goal {
-                if T::DOES_WORK {
-                    let mut visited_pos = goal;
-                    let mut path = T::new();
-                    path.push(pos);
-                    while let Some(next) = prev.get(&visited_pos) {
-                        visited_pos = *next;
-                        path.push(*next);
-                        if *next == start {
-                            path.finalize();
-                            return Some(path);
-                        }
-                    }
-                } else {
-                    return Some(T::new());
-                }
-            }
-
-            if costs.get(&pos).is_some_and(|v| cost.0 > *v) {
-                continue;
-            }
-
-            let moves = self.valid_moves(&pos);
-            for new_pos in moves {
-                if costs.get(&new_pos).is_none_or(|best_cost| cost.0 + 1 < *best_cost) {
-                    costs.set(&new_pos, cost.0 + 1);
-                    if T::DOES_WORK {
-                        prev.set(&new_pos, pos);
-                    }
-                    queue.push((Reverse(cost.0 + 1), new_pos));
-                }
-            }
-        }
-        None
+        // every cell is a state, so a grid beats hashing them
+        search::bfs_in(
+            start,
+            |pos| self.valid_moves(*pos),
+            |pos| *pos == goal,
+            self.map.same_shape(None),
+        )
     }
 }
 
@@ -229,6 +112,47 @@ pub fn part2_impl(input: &str, width: usize, height: usize, initial_safe_byte_co
     input_map.byte_stream[solution]
 }
 
+// Offline connectivity: with every byte placed, remove them in reverse, joining each freed cell to its free
+// neighbours. The first removal that connects start and goal is the byte that cut them apart.
+pub fn part2_impl_union_find(input: &str, width: usize, height: usize, initial_safe_byte_count: usize) -> (i64, i64) {
+    let mut input_map = MemoryMap::from_str(input, width, height);
+    // with no bytes at all the route is never blocked
+    let Some(last) = input_map.byte_stream.len().checked_sub(1) else {
+        panic!("no bytes block route");
+    };
+    input_map.place_bytes(0, last);
+
+    let mut sets = DisjointSet::new(input_map.map.data.len());
+    for pos in input_map.map.positions(|free| *free).collect_vec() {
+        input_map.join_free_neighbours(&mut sets, pos.into());
+    }
+
+    let start = input_map.map.pos(&(0, 0)) as usize;
+    let goal = input_map.map.pos(&(width as i64 - 1, height as i64 - 1)) as usize;
+    if sets.connected(start, goal) {
+        panic!("no bytes block route");
+    }
+    // a cell is only freed once its earliest byte is removed
+    let mut first_byte = input_map.map.same_shape(usize::MAX);
+    for (i, pos) in input_map.byte_stream.iter().enumerate() {
+        if first_byte.get(pos).is_some_and(|first| *first > i) {
+            first_byte.set(pos, i);
+        }
+    }
+    for i in (initial_safe_byte_count..input_map.byte_stream.len()).rev() {
+        let pos = input_map.byte_stream[i];
+        if first_byte.get(&pos) != Some(&i) {
+            continue;
+        }
+        input_map.map.set(&pos, true);
+        input_map.join_free_neighbours(&mut sets, pos);
+        if sets.connected(start, goal) {
+            return pos;
+        }
+    }
+    panic!("route blocked by the initial safe bytes");
+}
+
 #[aoc(day18, part1)]
 pub fn part1(input: &str) -> usize {
     part1_impl(input, 71, 71, 1024)
@@ -240,6 +164,12 @@ pub fn part2(input: &str) -> String {
     format!("{},{}", sol.0, sol.1)
 }
 
+#[aoc(day18, part2, UnionFind)]
+pub fn part2_union_find(input: &str) -> String {
+    let sol = part2_impl_union_find(input, 71, 71, 1024);
+    format!("{},{}", sol.0, sol.1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,4 +213,19 @@ mod tests {
     fn part2_example_brute() {
         assert_eq!(part2_impl_brute(EXAMPLE, 7, 7, 12,), (6, 1));
     }
+
+    #[test]
+    fn part2_example_union_find() {
+        assert_eq!(part2_impl_union_find(EXAMPLE, 7, 7, 12), (6, 1));
+        // later bytes after the blocking one, including a repeat of a cell already blocked
+        let extended = format!("{}\n0,3\n6,1\n3,6", EXAMPLE);
+        assert_eq!(part2_impl_union_find(&extended, 7, 7, 12), (6, 1));
+        assert_eq!(part2_impl(&extended, 7, 7, 12), (6, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "no bytes block route")]
+    fn part2_union_find_no_bytes() {
+        part2_impl_union_find("", 7, 7, 0);
+    }
 }
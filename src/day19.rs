@@ -1,4 +1,5 @@
 use aoc_runner_derive::aoc;
+use grid::CellCodec;
 use itertools::Itertools;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rustc_hash::FxHashMap;
@@ -14,29 +15,25 @@ enum Stripe {
     Green = b'g',
 }
 
-impl From<&u8> for Stripe {
-    fn from(value: &u8) -> Self {
-        match value {
-            b'w' => Self::White,
-            b'u' => Self::Blue,
-            b'b' => Self::Black,
-            b'r' => Self::Red,
-            b'g' => Self::Green,
-            _ => unimplemented!(),
+impl CellCodec for Stripe {
+    fn decode(symbol: u8) -> Option<Self> {
+        match symbol {
+            b'w' => Some(Self::White),
+            b'u' => Some(Self::Blue),
+            b'b' => Some(Self::Black),
+            b'r' => Some(Self::Red),
+            b'g' => Some(Self::Green),
+            _ => None,
         }
     }
-}
-
-impl From<&Stripe> for char {
-    fn from(val: &Stripe) -> Self {
-        let v = *val as u8;
-        v.into()
+    fn encode(&self) -> char {
+        *self as u8 as char
     }
 }
 
 impl Display for Stripe {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_char(self.into())
+        f.write_char(self.encode())
     }
 }
 
@@ -47,7 +44,10 @@ struct Design {
 
 impl From<&[u8]> for Design {
     fn from(input: &[u8]) -> Self {
-        let stripes = input.iter().map(|c| c.into()).collect();
+        let stripes = input
+            .iter()
+            .map(|c| Stripe::decode(*c).unwrap_or_else(|| panic!("unknown stripe {:?}", *c as char)))
+            .collect();
         Self { stripes }
     }
 }
@@ -55,7 +55,7 @@ impl From<&[u8]> for Design {
 impl Display for Design {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for stripe in &self.stripes {
-            f.write_char(stripe.into())?;
+            f.write_char(stripe.encode())?;
         }
         Ok(())
     }
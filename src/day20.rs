@@ -1,111 +1,270 @@
 use aoc_runner_derive::aoc;
-use grid::{AsCoord2d, Coord2d, Grid};
+use grid::{search, AsCoord2d, Coord2d, Grid};
 use itertools::Itertools;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::VecDeque;
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
 
-struct RaceTrack {
+pub struct RaceTrack {
     map: Grid<u8>,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
-struct State {
-    pos: Coord2d,
-    cost: u64,
+/// A shortcut through the walls, from a track square to a later one
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Cheat {
+    /// The last track square before the cheat
+    pub start: Coord2d,
+    /// The track square where the cheat ends
+    pub end: Coord2d,
+    /// Picoseconds spent cheating
+    pub length: u64,
+    /// Picoseconds saved compared to following the track
+    pub savings: u64,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct CheatState {
-    s: State,
+/// Every cheat on a track that saves time, sorted by start then end
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheatCatalogue {
+    cheats: Vec<Cheat>,
 }
 
-const DIRECTIONS: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+impl CheatCatalogue {
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+    /// The number of cheats saving each amount of time
+    pub fn histogram(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        for cheat in &self.cheats {
+            *histogram.entry(cheat.savings).or_insert(0) += 1;
+        }
+        histogram
+    }
+    /// Only the cheats whose length is in `range`
+    pub fn with_length<R: RangeBounds<u64>>(&self, range: R) -> Self {
+        Self {
+            cheats: self
+                .cheats
+                .iter()
+                .filter(|c| range.contains(&c.length))
+                .copied()
+                .collect(),
+        }
+    }
+    /// Only the cheats whose savings are in `range`
+    pub fn saving<R: RangeBounds<u64>>(&self, range: R) -> Self {
+        Self {
+            cheats: self
+                .cheats
+                .iter()
+                .filter(|c| range.contains(&c.savings))
+                .copied()
+                .collect(),
+        }
+    }
+}
 
-impl RaceTrack {
-    fn valid_moves<'a>(&'a self, CheatState { s: state }: &'a CheatState) -> impl Iterator<Item = CheatState> + 'a {
-        DIRECTIONS
-            .iter()
-            .map(|dir| state.pos + dir)
-            .filter_map(move |pos| match &self.map.get(&pos) {
-                Some(b'.') | Some(b'S') | Some(b'E') => Some(CheatState {
-                    s: State {
-                        pos,
-                        cost: state.cost + 1,
-                    },
-                }),
-                _ => None,
-            })
+/// Counts of values at least some threshold over ranges of a fixed list, as a merge sort tree: each node of a segment
+/// tree holds the sorted values beneath it, so a range query is O(log² n)
+struct SortedRanges {
+    tree: Vec<Vec<i64>>,
+}
+
+impl SortedRanges {
+    fn new(values: &[i64]) -> Self {
+        let n = values.len();
+        let mut tree = vec![Vec::new(); 2 * n];
+        for (i, val) in values.iter().enumerate() {
+            tree[n + i] = vec![*val];
+        }
+        for i in (1..n).rev() {
+            tree[i] = tree[2 * i].iter().merge(&tree[2 * i + 1]).copied().collect();
+        }
+        Self { tree }
     }
-    fn path_costs(&self, start: Coord2d, goal: Coord2d) -> Grid<Option<u64>> {
-        let mut queue = VecDeque::new();
-        let mut visited = self.map.same_shape(None);
-
-        let start_state = CheatState {
-            s: State { pos: start, cost: 0 },
-        };
-        visited.set(&start, Some(0));
-        queue.push_back(start_state);
-
-        while let Some(state) = queue.pop_front() {
-            if state.s.pos == goal {
-                return visited;
+    /// How many of `values[start..end]` are at least `threshold`
+    fn count_at_least(&self, start: usize, end: usize, threshold: i64) -> usize {
+        let n = self.tree.len() / 2;
+        let at_least = |node: &Vec<i64>| node.len() - node.partition_point(|v| *v < threshold);
+        let (mut start, mut end) = (start + n, end + n);
+        let mut count = 0;
+        while start < end {
+            if start & 1 == 1 {
+                count += at_least(&self.tree[start]);
+                start += 1;
             }
-
-            let moves = self.valid_moves(&state);
-            for new_state in moves {
-                if visited.get(&new_state.s.pos).unwrap().is_some() {
-                    continue;
-                }
-                visited.set(&new_state.s.pos, Some(new_state.s.cost));
-                queue.push_back(new_state);
+            if end & 1 == 1 {
+                end -= 1;
+                count += at_least(&self.tree[end]);
             }
+            start /= 2;
+            end /= 2;
         }
-        panic!("no path");
-    }
-
-    fn find_cheats(&self, path: &Vec<Coord2d>, costs: &Grid<Option<u64>>, min: u64) -> i64 {
-        let mut n = 0;
-        for pos in path {
-            let local_cost = costs.get(pos).unwrap().unwrap();
-            for ofs in DIRECTIONS {
-                let cheat_exit = (pos.x() + ofs.0 * 2, pos.y() + ofs.1 * 2);
-                if let Some(Some(cheat_cost)) = costs.get(&cheat_exit) {
-                    if *cheat_cost > local_cost + 2 {
-                        let cheat_savings = cheat_cost - local_cost - 2;
-                        if cheat_savings >= min {
-                            n += 1;
-                        }
-                    }
+        count
+    }
+}
+
+/// The track squares of one row, sorted by x
+struct RowIndex {
+    xs: Vec<i64>,
+    /// `cost - x` for each square, to score cheats ending right of their start
+    right: SortedRanges,
+    /// `cost + x` for each square, to score cheats ending left of their start
+    left: SortedRanges,
+}
+
+/// Track squares bucketed by row for counting cheats. A cheat from cost `c` at `x` to cost `c'` at `x'`, `dy` rows
+/// away, saves `c' - c - |x' - x| - |dy|`; for a fixed side of `x` the terms of the end square separate out to
+/// `c' ∓ x'`, so each row of the diamond of reachable ends is a single range count over those keys.
+struct CheatIndex {
+    rows: Vec<RowIndex>,
+}
+
+impl CheatIndex {
+    fn new(costs: &Grid<Option<u64>>) -> Self {
+        let rows = (0..costs.height() as i64)
+            .map(|y| {
+                let squares = (0..costs.width() as i64)
+                    .filter_map(|x| costs.get(&(x, y)).unwrap().map(|c| (x, c as i64)))
+                    .collect_vec();
+                RowIndex {
+                    xs: squares.iter().map(|(x, _)| *x).collect(),
+                    right: SortedRanges::new(&squares.iter().map(|(x, c)| c - x).collect_vec()),
+                    left: SortedRanges::new(&squares.iter().map(|(x, c)| c + x).collect_vec()),
                 }
-            }
+            })
+            .collect();
+        Self { rows }
+    }
+    /// The number of cheats of up to `max_length` from the square at `pos` with cost `cost` saving at least `min`
+    fn count_from(&self, pos: &Coord2d, cost: i64, max_length: i64, min: i64) -> usize {
+        let mut count = 0;
+        for dy in -max_length..=max_length {
+            let Some(row) = usize::try_from(pos.y + dy).ok().and_then(|y| self.rows.get(y)) else {
+                continue;
+            };
+            let reach = max_length - dy.abs();
+            let lo = row.xs.partition_point(|x| *x < pos.x - reach);
+            let mid = row.xs.partition_point(|x| *x < pos.x);
+            let hi = row.xs.partition_point(|x| *x <= pos.x + reach);
+            count += row.right.count_at_least(mid, hi, min + cost + dy.abs() - pos.x);
+            count += row.left.count_at_least(lo, mid, min + cost + dy.abs() + pos.x);
         }
-        n
+        count
+    }
+}
+
+impl RaceTrack {
+    fn valid_moves(&self, pos: Coord2d) -> impl Iterator<Item = Coord2d> + '_ {
+        self.map
+            .neighbors4(&pos)
+            .filter(|(_, c)| matches!(c, b'.' | b'S' | b'E'))
+            .map(|(pos, _)| pos)
+    }
+    fn path_costs(&self, start: Coord2d, goal: Coord2d) -> Grid<Option<u64>> {
+        let distances = search::bfs_distances(start, |pos| self.valid_moves(*pos));
+        if !distances.contains_key(&goal) {
+            panic!("no path");
+        }
+
+        let mut costs = self.map.same_shape(None);
+        for (pos, cost) in distances {
+            costs.set(&pos, Some(cost as u64));
+        }
+        costs
+    }
+
+    /// Cost to reach each track square from the start, `None` for walls
+    fn costs(&self) -> Grid<Option<u64>> {
+        let start = self.map.find(&b'S').unwrap();
+        let goal = self.map.find(&b'E').unwrap();
+        self.path_costs(start, goal)
     }
 
     fn taxi_dist<A: AsCoord2d, B: AsCoord2d>(from: &A, to: &B) -> u64 {
         from.x().abs_diff(to.x()) + from.y().abs_diff(to.y())
     }
 
-    fn find_cheats_n(&self, path: &Vec<Coord2d>, costs: &Grid<Option<u64>>, max_length: u64, min: u64) -> i64 {
+    /// The number of cheats of up to `max_length` picoseconds saving at least `min` (and at least 1)
+    pub fn count_cheats(&self, max_length: u64, min: u64) -> i64 {
+        let costs = self.costs();
+        let index = CheatIndex::new(&costs);
+        let path: Vec<_> = costs.par_positions(|c| c.is_some()).collect();
         path.par_iter()
-            .map_with(costs, |costs, pos| {
-                let from_cost = costs.get(pos).unwrap().unwrap();
-                let mut n = 0;
-                for x in pos.x - max_length as i64 - 1..=pos.x + max_length as i64 {
-                    for y in pos.y - max_length as i64 - 1..=pos.y + max_length as i64 {
-                        let dist = Self::taxi_dist(pos, &(x, y));
-                        if dist <= max_length && dist >= 2 {
-                            if let Some(Some(to_cost)) = costs.get(&(x, y)) {
-                                if *to_cost > (from_cost + dist) && (to_cost - (from_cost + dist) >= min) {
-                                    n += 1;
-                                }
-                            }
+            .map(|pos| {
+                index.count_from(
+                    pos,
+                    costs.get(pos).unwrap().unwrap() as i64,
+                    max_length as i64,
+                    min.max(1) as i64,
+                )
+            })
+            .sum::<usize>() as i64
+    }
+
+    /// Every cheat of up to `max_length` picoseconds that saves time
+    pub fn cheats(&self, max_length: u64) -> CheatCatalogue {
+        let costs = self.costs();
+        let mut cheats = Vec::new();
+        for (start, from_cost) in costs.iter_enumerated() {
+            let Some(from_cost) = from_cost else { continue };
+            let reach = max_length as i64;
+            for y in start.y - reach..=start.y + reach {
+                for x in start.x - reach..=start.x + reach {
+                    let length = Self::taxi_dist(&start, &(x, y));
+                    if !(2..=max_length).contains(&length) {
+                        continue;
+                    }
+                    if let Some(Some(to_cost)) = costs.get(&(x, y)) {
+                        if *to_cost > from_cost + length {
+                            cheats.push(Cheat {
+                                start,
+                                end: Coord2d { x, y },
+                                length,
+                                savings: to_cost - from_cost - length,
+                            });
                         }
                     }
                 }
-                n
-            })
-            .sum()
+            }
+        }
+        cheats.sort();
+        CheatCatalogue { cheats }
+    }
+
+    /// Draw `cheat` on the track the way the puzzle does: the squares it passes through, moving horizontally then
+    /// vertically, are marked `*`, except the first which is `1` and the end which is `2`
+    pub fn render_cheat(&self, cheat: &Cheat) -> String {
+        let mut map = self.map.clone();
+        let (dx, dy) = (
+            (cheat.end.x - cheat.start.x).signum(),
+            (cheat.end.y - cheat.start.y).signum(),
+        );
+        let mut pos = cheat.start;
+        let mut route = Vec::new();
+        while pos != cheat.end {
+            if pos.x != cheat.end.x {
+                pos.x += dx;
+            } else {
+                pos.y += dy;
+            }
+            route.push(pos);
+        }
+        for (i, pos) in route.iter().enumerate() {
+            let mark = match i {
+                0 => b'1',
+                _ if *pos == cheat.end => b'2',
+                _ => b'*',
+            };
+            map.set(pos, mark);
+        }
+        map.to_string()
     }
 }
 
@@ -115,33 +274,11 @@ fn parse(input: &str) -> RaceTrack {
 }
 
 fn part1_impl(input: &str, cheat_min: u64) -> i64 {
-    let track = parse(input);
-    let start = track.map.find(&b'S').unwrap();
-    let goal = track.map.find(&b'E').unwrap();
-    let costs = track.path_costs(start, goal);
-    let path_squares = costs
-        .data
-        .iter()
-        .enumerate()
-        .filter(|(_i, c)| c.is_some())
-        .filter_map(|(i, _)| track.map.coord(i as i64))
-        .collect_vec();
-    track.find_cheats(&path_squares, &costs, cheat_min)
+    parse(input).count_cheats(2, cheat_min)
 }
 
 fn part2_impl(input: &str, max_length: u64, cheat_min: u64) -> i64 {
-    let track = parse(input);
-    let start = track.map.find(&b'S').unwrap();
-    let goal = track.map.find(&b'E').unwrap();
-    let costs = track.path_costs(start, goal);
-    let path_squares = costs
-        .data
-        .iter()
-        .enumerate()
-        .filter(|(_i, c)| c.is_some())
-        .filter_map(|(i, _)| track.map.coord(i as i64))
-        .collect_vec();
-    track.find_cheats_n(&path_squares, &costs, max_length, cheat_min)
+    parse(input).count_cheats(max_length, cheat_min)
 }
 
 #[aoc(day20, part1)]
@@ -183,4 +320,142 @@ mod tests {
         assert_eq!(part2_impl(EXAMPLE, 2, 0), 44);
         assert_eq!(part2_impl(EXAMPLE, 20, 50), 285);
     }
+
+    #[test]
+    fn cheat_histogram() {
+        let cheats = parse(EXAMPLE).cheats(2);
+        assert_eq!(cheats.len(), 44);
+        assert_eq!(
+            cheats.histogram(),
+            BTreeMap::from([
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1)
+            ])
+        );
+        assert!(cheats.iter().all(|c| c.length == 2));
+    }
+
+    #[test]
+    fn cheat_filters() {
+        let cheats = parse(EXAMPLE).cheats(20);
+        assert_eq!(
+            cheats.saving(50..).histogram(),
+            BTreeMap::from([
+                (50, 32),
+                (52, 31),
+                (54, 29),
+                (56, 39),
+                (58, 25),
+                (60, 23),
+                (62, 20),
+                (64, 19),
+                (66, 12),
+                (68, 14),
+                (70, 12),
+                (72, 22),
+                (74, 4),
+                (76, 3)
+            ])
+        );
+        assert_eq!(cheats.saving(50..).len(), 285);
+        assert_eq!(cheats.with_length(..=2), parse(EXAMPLE).cheats(2));
+        assert_eq!(cheats.with_length(..=2).saving(64..=64).len(), 1);
+        assert!(cheats.saving(77..).is_empty());
+    }
+
+    #[test]
+    fn cheat_render() {
+        let track = parse(EXAMPLE);
+        let cheats = track.cheats(2).saving(64..);
+        let cheat = cheats.iter().next().unwrap();
+        assert_eq!(
+            track.render_cheat(cheat).trim_end(),
+            "###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..21...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############"
+        );
+        assert_eq!(
+            *cheat,
+            Cheat {
+                start: Coord2d { x: 7, y: 7 },
+                end: Coord2d { x: 5, y: 7 },
+                length: 2,
+                savings: 64
+            }
+        );
+    }
+
+    /// A serpentine track of `rows` corridors `width` squares long, separated by walls `wall` squares thick
+    fn serpentine(width: usize, rows: usize, wall: usize) -> String {
+        let mut lines = vec![vec![b'#'; width + 2]; rows * (wall + 1) + 1];
+        for row in 0..rows {
+            let y = 1 + row * (wall + 1);
+            lines[y][1..=width].fill(b'.');
+            if row + 1 < rows {
+                let x = if row % 2 == 0 { width } else { 1 };
+                for line in &mut lines[y + 1..=y + wall] {
+                    line[x] = b'.';
+                }
+            }
+        }
+        lines[1][1] = b'S';
+        let last = 1 + (rows - 1) * (wall + 1);
+        lines[last][if rows % 2 == 0 { 1 } else { width }] = b'E';
+        lines.into_iter().map(|l| String::from_utf8(l).unwrap()).join("\n")
+    }
+
+    #[test]
+    fn counting_matches_catalogue() {
+        let track = parse(EXAMPLE);
+        for max_length in [2, 3, 6, 20, 30] {
+            let cheats = track.cheats(max_length);
+            for min in [0, 1, 2, 30, 50, 76, 77] {
+                assert_eq!(
+                    track.count_cheats(max_length, min),
+                    cheats.saving(min.max(1)..).len() as i64,
+                    "length {} min {}",
+                    max_length,
+                    min
+                );
+            }
+        }
+
+        let track = parse(&serpentine(60, 12, 2));
+        for (max_length, min) in [(2, 1), (7, 20), (25, 100)] {
+            assert_eq!(
+                track.count_cheats(max_length, min),
+                track.cheats(max_length).saving(min..).len() as i64
+            );
+        }
+    }
+
+    #[test]
+    fn long_cheats() {
+        // the diamond of reachable ends spills well past every edge of the grid
+        let track = parse(&serpentine(30, 10, 3));
+        let count = track.count_cheats(120, 100);
+        assert!(count > 0);
+        assert_eq!(count, track.cheats(120).saving(100..).len() as i64);
+    }
 }
@@ -1,173 +1,155 @@
 use aoc_runner_derive::aoc;
-use rustc_hash::FxHashMap;
 use std::iter::repeat_n;
 
-#[derive(Clone, Debug)]
-enum KeypadRobot {
-    Number(NumberKeypadRobot),
-    Direction(DirectionKeypadRobot),
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Keypad {
+    Number,
+    Direction,
 }
 
-impl KeypadRobot {
-    fn press(&mut self, target: u8) -> Vec<FxHashMap<Vec<u8>, usize>> {
-        match self {
-            Self::Number(r) => r.press(target),
-            Self::Direction(r) => r.press(target),
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-struct NumberKeypadRobot {
-    pointing_at: u8,
-}
+/// Buttons of the directional keypad, in the order used to index [PressCosts] tables
+const DIRECTION_BUTTONS: [u8; 5] = [b'^', b'A', b'<', b'v', b'>'];
 
-impl NumberKeypadRobot {
-    fn pos_of(button: u8) -> (i8, i8) {
-        match button {
-            b'7' => (0, 0),
-            b'8' => (1, 0),
-            b'9' => (2, 0),
-            b'4' => (0, 1),
-            b'5' => (1, 1),
-            b'6' => (2, 1),
-            b'1' => (0, 2),
-            b'2' => (1, 2),
-            b'3' => (2, 2),
-            b'X' => (0, 3),
-            b'0' => (1, 3),
-            b'A' => (2, 3),
-            c => unimplemented!("unexpected character {}", c),
+impl Keypad {
+    fn pos_of(&self, button: u8) -> (i8, i8) {
+        match self {
+            Keypad::Number => match button {
+                b'7' => (0, 0),
+                b'8' => (1, 0),
+                b'9' => (2, 0),
+                b'4' => (0, 1),
+                b'5' => (1, 1),
+                b'6' => (2, 1),
+                b'1' => (0, 2),
+                b'2' => (1, 2),
+                b'3' => (2, 2),
+                b'X' => (0, 3),
+                b'0' => (1, 3),
+                b'A' => (2, 3),
+                c => unimplemented!("unexpected character {}", c),
+            },
+            Keypad::Direction => match button {
+                b'X' => (0, 0),
+                b'^' => (1, 0),
+                b'A' => (2, 0),
+                b'<' => (0, 1),
+                b'v' => (1, 1),
+                b'>' => (2, 1),
+                c => unimplemented!("unexpected char {}", c),
+            },
         }
     }
-}
-impl NumberKeypadRobot {
-    fn new() -> Self {
-        Self { pointing_at: b'A' }
-    }
-    fn press(&mut self, target: u8) -> Vec<FxHashMap<Vec<u8>, usize>> {
-        let cur_pos = Self::pos_of(self.pointing_at);
-        let goal_pos = Self::pos_of(target);
+    /// The candidate move sequences (each ending in `A` to press the button) that take the pointer from `from` to
+    /// `to` without passing over the gap
+    fn paths(&self, from: u8, to: u8) -> Vec<Vec<u8>> {
+        let cur_pos = self.pos_of(from);
+        let goal_pos = self.pos_of(to);
         let x_ofs = goal_pos.0 - cur_pos.0;
         let y_ofs = goal_pos.1 - cur_pos.1;
+        let x_moves = repeat_n(if x_ofs > 0 { b'>' } else { b'<' }, x_ofs.unsigned_abs() as usize);
+        let y_moves = repeat_n(if y_ofs > 0 { b'v' } else { b'^' }, y_ofs.unsigned_abs() as usize);
 
         let mut paths = Vec::new();
         // NOTE: no need to consider zig-zags since those paths will always require more button presses going back and forth
-        if (cur_pos.0 + x_ofs, cur_pos.1) != Self::pos_of(b'X') {
-            let mut x_first = Vec::new();
-            x_first.extend(repeat_n(if x_ofs > 0 { b'>' } else { b'<' }, x_ofs.abs() as usize));
-            x_first.extend(repeat_n(if y_ofs > 0 { b'v' } else { b'^' }, y_ofs.abs() as usize));
-            x_first.push(b'A');
-            paths.push({
-                let mut h = FxHashMap::default();
-                h.insert(x_first, 1);
-                h
-            });
+        if (cur_pos.0 + x_ofs, cur_pos.1) != self.pos_of(b'X') {
+            paths.push(x_moves.clone().chain(y_moves.clone()).chain([b'A']).collect());
         }
-        if (cur_pos.0, cur_pos.1 + y_ofs) != Self::pos_of(b'X') {
-            let mut y_first = Vec::new();
-            y_first.extend(repeat_n(if y_ofs > 0 { b'v' } else { b'^' }, y_ofs.abs() as usize));
-            y_first.extend(repeat_n(if x_ofs > 0 { b'>' } else { b'<' }, x_ofs.abs() as usize));
-            y_first.push(b'A');
-            paths.push({
-                let mut h = FxHashMap::default();
-                h.insert(y_first, 1);
-                h
-            });
+        if (cur_pos.0, cur_pos.1 + y_ofs) != self.pos_of(b'X') {
+            paths.push(y_moves.chain(x_moves).chain([b'A']).collect());
         }
         if paths.is_empty() {
             panic!("all paths lead to the void");
         }
         paths.dedup();
-        self.pointing_at = target;
         paths
     }
 }
 
-#[derive(Clone, Debug)]
-struct DirectionKeypadRobot {
-    pointing_at: u8,
-    child: Option<Box<KeypadRobot>>,
-    id: usize,
+fn direction_idx(button: u8) -> usize {
+    DIRECTION_BUTTONS.iter().position(|b| *b == button).unwrap()
 }
 
-impl DirectionKeypadRobot {
-    fn pos_of(target: u8) -> (i8, i8) {
-        match target {
-            b'X' => (0, 0),
-            b'^' => (1, 0),
-            b'A' => (2, 0),
-            b'<' => (0, 1),
-            b'v' => (1, 1),
-            b'>' => (2, 1),
-            c => unimplemented!("unexpected char {}", c),
-        }
-    }
-    fn move_to(&mut self, target: u8) -> Vec<u8> {
-        let cur_pos = Self::pos_of(self.pointing_at);
-        let goal_pos = Self::pos_of(target);
-        let x_ofs = goal_pos.0 - cur_pos.0;
-        let y_ofs = goal_pos.1 - cur_pos.1;
-
-        self.pointing_at = target;
-
-        if (cur_pos.0 + x_ofs, cur_pos.1) != Self::pos_of(b'X') {
-            let mut x_first = Vec::new();
-            x_first.extend(repeat_n(if x_ofs > 0 { b'>' } else { b'<' }, x_ofs.abs() as usize));
-            x_first.extend(repeat_n(if y_ofs > 0 { b'v' } else { b'^' }, y_ofs.abs() as usize));
-            x_first.push(b'A');
-            return x_first;
-        }
-        if (cur_pos.0, cur_pos.1 + y_ofs) != Self::pos_of(b'X') {
-            let mut y_first = Vec::new();
-            y_first.extend(repeat_n(if y_ofs > 0 { b'v' } else { b'^' }, y_ofs.abs() as usize));
-            y_first.extend(repeat_n(if x_ofs > 0 { b'>' } else { b'<' }, x_ofs.abs() as usize));
-            y_first.push(b'A');
-            return y_first;
-        }
-        panic!("all routes lead to the void");
-    }
-    fn path_to(&mut self, moves: &Vec<u8>) -> Vec<u8> {
-        let prev_point = self.pointing_at;
-        let mut path = Vec::new();
-        for m in moves {
-            path.append(&mut self.move_to(*m));
-        }
-        self.pointing_at = prev_point;
-        path
-    }
+/// Memoized cost, in human key presses, of moving between and pressing directional keypad buttons.
+///
+/// `layers[depth][from][to]` is the cost of pressing `to` on a directional keypad whose pointer starts at `from`,
+/// when `depth` robots sit between the human and that keypad. Every robot's pointer rests on `A` between presses,
+/// so a press at one depth is independent of the others and costs can be built up one layer at a time.
+struct PressCosts {
+    layers: Vec<[[u128; 5]; 5]>,
+}
 
-    fn new(id: usize, child: Option<Box<KeypadRobot>>) -> Self {
+impl PressCosts {
+    fn new() -> Self {
+        // the human presses buttons directly
         Self {
-            id,
-            pointing_at: b'A',
-            child,
+            layers: vec![[[1; 5]; 5]],
         }
     }
-    fn press(&mut self, target: u8) -> Vec<FxHashMap<Vec<u8>, usize>> {
-        let child_frequencies = self.child.as_mut().unwrap().press(target);
-        let mut my_frequencies = Vec::new();
-
-        for freq_set in child_frequencies {
-            let mut local_freqs = FxHashMap::default();
-            for (moves, count) in freq_set {
-                assert_eq!(self.pointing_at, b'A');
-                let path = self.path_to(&moves);
-                for path_move in path.split_inclusive(|m| *m == b'A') {
-                    let entry = local_freqs.entry(path_move.to_vec()).or_insert(0);
-                    *entry = *entry + count;
+    /// Cost of typing `sequence` (starting from `A`) on a keypad whose presses cost `layer`
+    fn sequence_cost(layer: &[[u128; 5]; 5], sequence: &[u8]) -> u128 {
+        let mut from = direction_idx(b'A');
+        let mut cost = 0;
+        for button in sequence {
+            let to = direction_idx(*button);
+            cost = layer[from][to].saturating_add(cost);
+            from = to;
+        }
+        cost
+    }
+    fn layer(&mut self, depth: usize) -> &[[u128; 5]; 5] {
+        while self.layers.len() <= depth {
+            let prev = self.layers.last().unwrap();
+            let mut next = [[0; 5]; 5];
+            for (from_idx, from) in DIRECTION_BUTTONS.iter().enumerate() {
+                for (to_idx, to) in DIRECTION_BUTTONS.iter().enumerate() {
+                    next[from_idx][to_idx] = Keypad::Direction
+                        .paths(*from, *to)
+                        .iter()
+                        .map(|path| Self::sequence_cost(prev, path))
+                        .min()
+                        .unwrap();
                 }
             }
-            my_frequencies.push(local_freqs);
+            self.layers.push(next);
         }
-        my_frequencies
+        &self.layers[depth]
+    }
+    /// Minimum human key presses to type `code` on the numeric keypad, with `depth` robot-operated directional
+    /// keypads in between. Saturates at `u128::MAX`.
+    fn min_presses(&mut self, code: &[u8], depth: usize) -> u128 {
+        let layer = self.layer(depth);
+        let mut from = b'A';
+        let mut presses = 0;
+        for button in code {
+            presses = Keypad::Number
+                .paths(from, *button)
+                .iter()
+                .map(|path| Self::sequence_cost(layer, path))
+                .min()
+                .unwrap()
+                .saturating_add(presses);
+            from = *button;
+        }
+        presses
+    }
+}
+
+/// Minimum human key presses to type `code` on the numeric keypad, with `depth` robot-operated directional keypads in
+/// between. The count grows by roughly 2.5x per layer and overflows a `u128` after about 94 layers (exactly 94 for a
+/// four-button code like `029A`), so deeper queries return `None`, as does a `code` with anything but the keys
+/// `0`-`9` and `A`.
+pub fn min_presses(code: &str, depth: usize) -> Option<u128> {
+    if !code.bytes().all(|b| b.is_ascii_digit() || b == b'A') {
+        return None;
     }
+    let presses = PressCosts::new().min_presses(code.as_bytes(), depth);
+    (presses != u128::MAX).then_some(presses)
 }
 
 struct Code(Vec<u8>);
 
 impl Code {
-    fn num_val(&self) -> i64 {
+    fn num_val(&self) -> u128 {
         String::from_utf8_lossy(&self.0.as_slice()[0..3]).parse().unwrap()
     }
 }
@@ -180,43 +162,22 @@ fn parse(input: &str) -> Vec<Code> {
     codes
 }
 
-fn run_robots(code: &Code, n: usize) -> i64 {
-    let numpad = Box::new(KeypadRobot::Number(NumberKeypadRobot::new()));
-
-    let mut robot = Box::new(KeypadRobot::Direction(DirectionKeypadRobot::new(0, Some(numpad))));
-    for i in 1..n {
-        let new_robot = Box::new(KeypadRobot::Direction(DirectionKeypadRobot::new(i, Some(robot))));
-        robot = new_robot
-    }
-
-    // let mut sol_freqs = Vec::new();
-    let mut sum = 0;
-    for button in &code.0 {
-        let paths = robot.press(*button).to_vec();
-        let best = paths
-            .iter()
-            .map(|bp| bp.iter().map(|(k, v)| k.len() * v).sum::<usize>())
-            .min()
-            .unwrap();
-        sum += best;
-    }
-    return sum as i64 * code.num_val();
+fn complexities(codes: &[Code], depth: usize) -> u128 {
+    let mut costs = PressCosts::new();
+    codes
+        .iter()
+        .map(|code| costs.min_presses(&code.0, depth) * code.num_val())
+        .sum()
 }
 
 #[aoc(day21, part1)]
-fn part1(input: &str) -> i64 {
-    let codes = parse(input);
-    codes.iter().map(|c| run_robots(c, 2)).sum::<i64>() as i64
+fn part1(input: &str) -> u128 {
+    complexities(&parse(input), 2)
 }
 
 #[aoc(day21, part2)]
-fn part2(input: &str) -> i64 {
-    let codes = parse(input);
-    for i in 0..26 {
-        let res = codes.iter().map(|c| run_robots(c, i)).sum::<i64>() as i64;
-        println!("{i}: {res}");
-    }
-    0
+fn part2(input: &str) -> u128 {
+    complexities(&parse(input), 25)
 }
 
 #[cfg(test)]
@@ -235,6 +196,22 @@ mod tests {
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(EXAMPLE), 0);
+        assert_eq!(part2(EXAMPLE), 154115708116294);
+    }
+
+    #[test]
+    fn presses_by_depth() {
+        assert_eq!(min_presses("029A", 0), Some("<A^A>^^AvvvA".len() as u128));
+        assert_eq!(
+            min_presses("029A", 1),
+            Some("v<<A>>^A<A>AvA<^AA>A<vAAA>^A".len() as u128)
+        );
+        assert_eq!(min_presses("029A", 2), Some(68));
+        assert_eq!(min_presses("029A", 94), Some(167203330628577046004700264293796308836));
+        assert_eq!(min_presses("029A", 95), None);
+        assert_eq!(min_presses("029A", 300), None);
+        assert_eq!(min_presses("", 300), Some(0));
+        assert_eq!(min_presses("02XA", 2), None);
+        assert_eq!(min_presses("029b", 2), None);
     }
 }
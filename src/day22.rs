@@ -1,15 +1,16 @@
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use rustc_hash::FxHashMap;
+use rayon::iter::ParallelIterator;
+use rayon::slice::ParallelSlice;
+use std::fmt::Display;
 
-#[derive(Debug, Eq, PartialEq, PartialOrd, Ord, Clone, Copy)]
-struct Change {
-    price: i8,
-    delta: i8,
-}
+pub type Secret = u64;
+
+/// Number of distinct windows of four price changes, each change being one of -9..=9
+pub const WINDOWS: usize = 19usize.pow(4);
 
-type Secret = u64;
+/// Buyers evolved together in [evolve_lanes]
+const LANES: usize = 8;
 
 fn evolve_secret(mut n: Secret) -> Secret {
     n = ((n * 64) ^ n) % 16777216;
@@ -18,14 +19,35 @@ fn evolve_secret(mut n: Secret) -> Secret {
     n
 }
 
-fn rounds(mut secret: Secret, n: Secret) -> Secret {
+/// Evolve a fixed block of secrets in lock-step, a shape the compiler can vectorise
+fn evolve_lanes(lanes: &mut [Secret; LANES]) {
+    for secret in lanes.iter_mut() {
+        *secret = evolve_secret(*secret);
+    }
+}
+
+pub fn rounds(mut secret: Secret, n: Secret) -> Secret {
     for _ in 0..n {
         secret = evolve_secret(secret)
     }
     secret
 }
 
-fn prices(mut secret: Secret, n: usize) -> Vec<i8> {
+/// [rounds] for every secret, evolved [LANES] at a time
+fn rounds_all(secrets: &[Secret], n: usize) -> Vec<Secret> {
+    let mut result = Vec::with_capacity(secrets.len());
+    for chunk in secrets.chunks(LANES) {
+        let mut lanes = [0; LANES];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+        for _ in 0..n {
+            evolve_lanes(&mut lanes);
+        }
+        result.extend_from_slice(&lanes[..chunk.len()]);
+    }
+    result
+}
+
+pub fn prices(mut secret: Secret, n: usize) -> Vec<i8> {
     let mut prices = vec![(secret % 10) as i8];
     for _ in 1..n {
         secret = evolve_secret(secret);
@@ -34,43 +56,297 @@ fn prices(mut secret: Secret, n: usize) -> Vec<i8> {
     prices
 }
 
-fn build_profit_map(prices: &[i8]) -> FxHashMap<[i8; 4], i8> {
-    let mut profits = FxHashMap::default();
-    let changes = prices
-        .windows(2)
-        .map(|a| Change {
-            price: a[1],
-            delta: a[1] - a[0],
-        })
-        .collect_vec();
-    for i in 3..changes.len() {
-        let seq: [i8; 4] = changes[i - 3..=i]
+/// Why a [SecretRng] can't be built from the given parameters
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RngError {
+    /// The modulus must be a power of two for the steps to be linear over GF(2)
+    Modulus(u64),
+    /// A zero shift would XOR the secret with itself, which can't be undone
+    ZeroShift,
+}
+
+impl Display for RngError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RngError::Modulus(m) => write!(f, "modulus {} is not a power of two", m),
+            RngError::ZeroShift => write!(f, "shift amounts must be non-zero"),
+        }
+    }
+}
+
+/// A linear map on secrets over GF(2), stored as the image of each bit
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Gf2Matrix {
+    columns: Vec<u64>,
+}
+
+impl Gf2Matrix {
+    fn identity(bits: u32) -> Self {
+        Self {
+            columns: (0..bits).map(|i| 1 << i).collect(),
+        }
+    }
+    pub fn apply(&self, x: u64) -> u64 {
+        self.columns
             .iter()
-            .map(|c| c.delta)
-            .collect_vec()
-            .try_into()
-            .unwrap();
-        profits.entry(seq).or_insert(changes[i].price);
+            .enumerate()
+            .filter(|(i, _)| x >> i & 1 == 1)
+            .fold(0, |acc, (_, col)| acc ^ col)
+    }
+    /// The map applying `other` then `self`
+    fn compose(&self, other: &Self) -> Self {
+        Self {
+            columns: other.columns.iter().map(|col| self.apply(*col)).collect(),
+        }
+    }
+}
+
+/// Where a seed's sequence of secrets repeats: after `tail` steps it enters a cycle of `period` secrets
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cycle {
+    pub tail: u64,
+    pub period: u64,
+}
+
+/// The secret number generator with configurable steps: shift left and XOR, shift right and XOR, shift left and XOR,
+/// each reduced modulo a power of two
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SecretRng {
+    shifts: [u32; 3],
+    bits: u32,
+}
+
+impl SecretRng {
+    /// The generator from the puzzle, identical to [evolve_secret]
+    pub const PUZZLE: SecretRng = SecretRng {
+        shifts: [6, 5, 11],
+        bits: 24,
+    };
+
+    /// A generator multiplying by 2^`left`, dividing by 2^`right`, then multiplying by 2^`left_again`
+    pub fn new(left: u32, right: u32, left_again: u32, modulus: u64) -> Result<Self, RngError> {
+        if modulus < 2 || !modulus.is_power_of_two() {
+            return Err(RngError::Modulus(modulus));
+        }
+        if left == 0 || right == 0 || left_again == 0 {
+            return Err(RngError::ZeroShift);
+        }
+        Ok(Self {
+            shifts: [left, right, left_again],
+            bits: modulus.trailing_zeros(),
+        })
+    }
+    fn mask(&self) -> u64 {
+        (1 << self.bits) - 1
+    }
+    fn shl(&self, x: u64, shift: u32) -> u64 {
+        x.checked_shl(shift).unwrap_or(0) & self.mask()
+    }
+    pub fn next(&self, mut x: u64) -> u64 {
+        x = (x ^ self.shl(x, self.shifts[0])) & self.mask();
+        x ^= x.checked_shr(self.shifts[1]).unwrap_or(0);
+        x ^ self.shl(x, self.shifts[2])
+    }
+    /// The secret `n` steps after `seed`, one step at a time
+    pub fn nth(&self, seed: u64, n: u64) -> u64 {
+        (0..n).fold(seed, |x, _| self.next(x))
     }
-    profits
+    /// The secret before `x`. Each step XORs in a shifted copy of itself, so repeating that with the partially
+    /// recovered value restores another `shift` bits each round.
+    pub fn previous(&self, x: u64) -> u64 {
+        let x = x & self.mask();
+        let unshift = |y: u64, shift: &dyn Fn(u64) -> u64, amount: u32| {
+            (0..self.bits.div_ceil(amount)).fold(y, |acc, _| y ^ shift(acc))
+        };
+        let x = unshift(x, &|v| self.shl(v, self.shifts[2]), self.shifts[2]);
+        let x = unshift(x, &|v| v.checked_shr(self.shifts[1]).unwrap_or(0), self.shifts[1]);
+        unshift(x, &|v| self.shl(v, self.shifts[0]), self.shifts[0])
+    }
+    /// The linear map advancing a secret by `n` steps, built by repeated squaring
+    pub fn jump_map(&self, mut n: u64) -> Gf2Matrix {
+        let mut step = Gf2Matrix {
+            columns: (0..self.bits).map(|i| self.next(1 << i)).collect(),
+        };
+        let mut result = Gf2Matrix::identity(self.bits);
+        while n > 0 {
+            if n & 1 == 1 {
+                result = step.compose(&result);
+            }
+            step = step.compose(&step);
+            n >>= 1;
+        }
+        result
+    }
+    /// The secret `n` steps after `seed`, in O(log n) matrix products
+    pub fn jump(&self, seed: u64, n: u64) -> u64 {
+        self.jump_map(n).apply(seed & self.mask())
+    }
+    /// Find the cycle `seed` falls into, using Brent's algorithm. Every step is invertible, so the tail is always 0
+    /// for a valid generator, but it is measured rather than assumed.
+    pub fn cycle(&self, seed: u64) -> Cycle {
+        let seed = seed & self.mask();
+        let (mut power, mut period) = (1, 1);
+        let mut tortoise = seed;
+        let mut hare = self.next(seed);
+        while tortoise != hare {
+            if power == period {
+                tortoise = hare;
+                power *= 2;
+                period = 0;
+            }
+            hare = self.next(hare);
+            period += 1;
+        }
+        let mut tortoise = seed;
+        let mut hare = self.nth(seed, period);
+        let mut tail = 0;
+        while tortoise != hare {
+            tortoise = self.next(tortoise);
+            hare = self.next(hare);
+            tail += 1;
+        }
+        Cycle { tail, period }
+    }
+}
+
+/// Shift a price change into a rolling window index, dropping the oldest change
+fn push_change(index: usize, delta: i8) -> usize {
+    index % 19usize.pow(3) * 19 + (delta + 9) as usize
+}
+
+/// The base-19 index of a window of four price changes, oldest change most significant
+pub fn window_index(changes: [i8; 4]) -> usize {
+    changes.into_iter().fold(0, push_change)
 }
 
-fn profit_for_sequence(changes: &[FxHashMap<[i8; 4], i8>], seq: &[i8]) -> i64 {
+/// The price changes of a window index
+pub fn window_changes(mut index: usize) -> [i8; 4] {
+    let mut changes = [0; 4];
+    for change in changes.iter_mut().rev() {
+        *change = (index % 19) as i8 - 9;
+        index /= 19;
+    }
     changes
-        .iter()
-        .filter_map(|inner| inner.get(seq).map(|v| *v as i64))
-        .sum()
 }
 
-fn find_best_sequence(changes: &[FxHashMap<[i8; 4], i8>]) -> [i8; 4] {
-    let possible_seqs = (0..4).map(|_| (-9..=9i8)).multi_cartesian_product().collect_vec();
-    let (best_seq, _best_profit) = possible_seqs
-        .par_iter()
-        .map_with(changes, |changes, seq| (seq, profit_for_sequence(changes, seq)))
-        .max_by(|(_, a), (_, b)| a.cmp(b))
-        .unwrap();
+/// Mark `index` as seen, returning whether it was new
+fn first_visit(seen: &mut [u64], index: usize) -> bool {
+    let (word, bit) = (index / 64, 1 << (index % 64));
+    let new = seen[word] & bit == 0;
+    seen[word] |= bit;
+    new
+}
 
-    best_seq.as_slice().try_into().unwrap()
+/// One sequence's entry in a [Market::top] report
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SequenceReport {
+    pub changes: [i8; 4],
+    /// Bananas earned across every buyer
+    pub total: u64,
+    /// The price each buyer sells at, or `None` if the sequence never appears for them
+    pub per_buyer: Vec<Option<i8>>,
+}
+
+/// Every buyer's prices, totalled per window of four changes. Each buyer sells at the first occurrence of a window.
+pub struct Market {
+    buyers: Vec<Secret>,
+    prices: usize,
+    /// Bananas per window index. 9 bananas per buyer means this holds up to 7281 buyers before saturating.
+    totals: Box<[u16; WINDOWS]>,
+}
+
+impl Market {
+    /// Simulate `prices` prices (including the initial one) for every buyer
+    pub fn new(buyers: Vec<Secret>, prices: usize) -> Self {
+        let totals = buyers
+            .par_chunks(LANES)
+            .fold(
+                || (vec![0u16; WINDOWS], vec![vec![0u64; WINDOWS.div_ceil(64)]; LANES]),
+                |(mut totals, mut seen), chunk| {
+                    Self::simulate(chunk, prices, &mut totals, &mut seen);
+                    (totals, seen)
+                },
+            )
+            .map(|(totals, _)| totals)
+            .reduce(
+                || vec![0u16; WINDOWS],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(a, b)| *a = a.saturating_add(b));
+                    a
+                },
+            );
+        Self {
+            buyers,
+            prices,
+            totals: totals.into_boxed_slice().try_into().unwrap(),
+        }
+    }
+    /// Add the first sale per window of up to [LANES] buyers to `totals`
+    fn simulate(chunk: &[Secret], prices: usize, totals: &mut [u16], seen: &mut [Vec<u64>]) {
+        let mut lanes = [0; LANES];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+        let mut last = lanes.map(|s| (s % 10) as i8);
+        let mut index = [0; LANES];
+        seen.iter_mut().for_each(|s| s.fill(0));
+        for step in 1..prices {
+            evolve_lanes(&mut lanes);
+            for lane in 0..chunk.len() {
+                let price = (lanes[lane] % 10) as i8;
+                index[lane] = push_change(index[lane], price - last[lane]);
+                last[lane] = price;
+                if step >= 4 && first_visit(&mut seen[lane], index[lane]) {
+                    totals[index[lane]] = totals[index[lane]].saturating_add(price as u16);
+                }
+            }
+        }
+    }
+    /// Bananas earned by selling at the first occurrence of `changes`
+    pub fn total(&self, changes: [i8; 4]) -> u64 {
+        self.totals[window_index(changes)] as u64
+    }
+    /// The price `buyer` sells at for `changes`, if it ever appears
+    pub fn buyer_price(&self, buyer: usize, changes: [i8; 4]) -> Option<i8> {
+        let target = window_index(changes);
+        let mut secret = self.buyers[buyer];
+        let mut index = 0;
+        for step in 1..self.prices {
+            let next = evolve_secret(secret);
+            let price = (next % 10) as i8;
+            index = push_change(index, price - (secret % 10) as i8);
+            secret = next;
+            if step >= 4 && index == target {
+                return Some(price);
+            }
+        }
+        None
+    }
+    /// The best sequence and its total. Ties go to the lowest window index.
+    pub fn best(&self) -> ([i8; 4], u64) {
+        let (index, total) = self
+            .totals
+            .iter()
+            .enumerate()
+            .max_by(|(i, a), (j, b)| a.cmp(b).then(j.cmp(i)))
+            .unwrap();
+        (window_changes(index), *total as u64)
+    }
+    /// The `n` most profitable sequences, best first, with what each buyer contributes
+    pub fn top(&self, n: usize) -> Vec<SequenceReport> {
+        self.totals
+            .iter()
+            .enumerate()
+            .sorted_by(|(i, a), (j, b)| b.cmp(a).then(i.cmp(j)))
+            .take(n)
+            .map(|(index, total)| {
+                let changes = window_changes(index);
+                SequenceReport {
+                    changes,
+                    total: *total as u64,
+                    per_buyer: (0..self.buyers.len()).map(|b| self.buyer_price(b, changes)).collect(),
+                }
+            })
+            .collect()
+    }
 }
 
 fn parse(input: &str) -> Vec<Secret> {
@@ -81,20 +357,15 @@ fn parse(input: &str) -> Vec<Secret> {
 pub fn part1(input: &str) -> Secret {
     let secrets = parse(input);
 
-    secrets.iter().map(|s| rounds(*s, 2000)).sum::<Secret>()
+    rounds_all(&secrets, 2000).iter().sum::<Secret>()
 }
 
 #[aoc(day22, part2)]
 pub fn part2(input: &str) -> i64 {
     let secrets = parse(input);
 
-    let price_changes = secrets
-        .iter()
-        .map(|s| build_profit_map(&prices(*s, 2000)))
-        .collect_vec();
-
-    let seq = find_best_sequence(&price_changes);
-    profit_for_sequence(&price_changes, &seq)
+    let market = Market::new(secrets, 2000);
+    market.best().1 as i64
 }
 
 #[cfg(test)]
@@ -129,18 +400,59 @@ mod tests {
     }
 
     #[test]
-    fn test_profit() {
+    fn test_rounds_all() {
+        let secrets = (1..20).collect_vec();
         assert_eq!(
-            profit_for_sequence(&vec![build_profit_map(&prices(123, 10))], &[-1, -1, 0, 2]),
-            6
+            rounds_all(&secrets, 2000),
+            secrets.iter().map(|s| rounds(*s, 2000)).collect_vec()
         );
-        let secrets = parse(EXAMPLE2);
+    }
 
-        let price_changes = secrets
-            .iter()
-            .map(|s| build_profit_map(&prices(*s, 2000)))
-            .collect_vec();
-        assert_eq!(profit_for_sequence(&price_changes, &[-2, 1, -1, 3]), 23);
+    #[test]
+    fn windows() {
+        assert_eq!(window_index([-9, -9, -9, -9]), 0);
+        assert_eq!(window_index([9, 9, 9, 9]), WINDOWS - 1);
+        for changes in [[-1, -1, 0, 2], [-2, 1, -1, 3], [9, -9, 0, 4]] {
+            assert_eq!(window_changes(window_index(changes)), changes);
+        }
+    }
+
+    #[test]
+    fn test_profit() {
+        let market = Market::new(vec![123], 10);
+        assert_eq!(market.total([-1, -1, 0, 2]), 6);
+        assert_eq!(market.buyer_price(0, [-1, -1, 0, 2]), Some(6));
+        assert_eq!(market.total([1, 2, 3, 4]), 0);
+        // prices 3 0 6 5 4 4 6 4 4 2: the window -1 0 2 -2 first sells at 4
+        assert_eq!(market.total([-1, 0, 2, -2]), 4);
+
+        let market = Market::new(parse(EXAMPLE2), 2000);
+        assert_eq!(market.total([-2, 1, -1, 3]), 23);
+        assert_eq!(market.best(), ([-2, 1, -1, 3], 23));
+    }
+
+    #[test]
+    fn top_sequences() {
+        let market = Market::new(parse(EXAMPLE2), 2000);
+        let top = market.top(5);
+        assert_eq!(top.len(), 5);
+        assert_eq!(top[0].changes, [-2, 1, -1, 3]);
+        assert_eq!(top[0].per_buyer, [Some(7), Some(7), None, Some(9)]);
+        for report in &top {
+            assert_eq!(report.total, market.total(report.changes));
+            assert_eq!(
+                report.total,
+                report.per_buyer.iter().flatten().map(|p| *p as u64).sum::<u64>()
+            );
+        }
+        assert!(top.windows(2).all(|w| w[0].total >= w[1].total));
+
+        // more buyers than lanes, with a ragged last block
+        let secrets = (1..=20).collect_vec();
+        let market = Market::new(secrets.clone(), 200);
+        let (changes, total) = market.best();
+        let by_buyer = (0..secrets.len()).filter_map(|b| market.buyer_price(b, changes));
+        assert_eq!(by_buyer.map(|p| p as u64).sum::<u64>(), total);
     }
 
     #[test]
@@ -152,4 +464,59 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(EXAMPLE2), 23);
     }
+
+    #[test]
+    fn rng_matches_rounds() {
+        let rng = SecretRng::PUZZLE;
+        assert_eq!(SecretRng::new(6, 5, 11, 16777216), Ok(rng));
+        for seed in [1, 10, 100, 2024, 123] {
+            assert_eq!(rng.next(seed), evolve_secret(seed));
+            assert_eq!(rng.nth(seed, 2000), rounds(seed, 2000));
+            assert_eq!(rng.jump(seed, 2000), rounds(seed, 2000));
+            assert_eq!(rng.jump(seed, 0), seed);
+        }
+        let map = rng.jump_map(2000);
+        assert_eq!(map.apply(1), 8685429);
+        assert_eq!(map.apply(10), 4700978);
+    }
+
+    #[test]
+    fn rng_inverse() {
+        let rng = SecretRng::PUZZLE;
+        for seed in [0, 1, 123, 15887950, 16777215] {
+            assert_eq!(rng.previous(rng.next(seed)), seed);
+            assert_eq!(rng.next(rng.previous(seed)), seed);
+        }
+        assert_eq!(rng.previous(15887950), 123);
+        // a shift wider than the secret leaves that step as the identity
+        let rng = SecretRng::new(3, 1, 40, 1 << 16).unwrap();
+        for seed in [1, 4242, 65535] {
+            assert_eq!(rng.previous(rng.next(seed)), seed);
+            assert_eq!(rng.jump(seed, 77), rng.nth(seed, 77));
+        }
+    }
+
+    #[test]
+    fn rng_errors() {
+        assert_eq!(SecretRng::new(6, 5, 11, 1000), Err(RngError::Modulus(1000)));
+        assert_eq!(SecretRng::new(6, 5, 11, 0), Err(RngError::Modulus(0)));
+        assert_eq!(SecretRng::new(0, 5, 11, 1 << 24), Err(RngError::ZeroShift));
+        assert_eq!(
+            RngError::Modulus(1000).to_string(),
+            "modulus 1000 is not a power of two"
+        );
+    }
+
+    #[test]
+    fn rng_cycles() {
+        let rng = SecretRng::new(3, 2, 5, 1 << 10).unwrap();
+        for seed in [1, 7, 500] {
+            let cycle = rng.cycle(seed);
+            assert_eq!(cycle.tail, 0);
+            assert_eq!(rng.nth(seed, cycle.period), seed);
+            assert_eq!(rng.jump(seed, cycle.period), seed);
+            assert!((1..cycle.period).all(|n| rng.nth(seed, n) != seed));
+        }
+        assert_eq!(rng.cycle(0), Cycle { tail: 0, period: 1 });
+    }
 }
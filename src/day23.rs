@@ -1,69 +1,151 @@
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display};
 
+/// A computer, identified by its name in the puzzle input
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-struct Node([char; 2]);
+pub struct Node<'a>(&'a str);
 
-impl Debug for Node {
+impl Debug for Node<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self.0.iter().join("")))
+        f.write_str(self.0)
     }
 }
 
-impl Display for Node {
+impl Display for Node<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self.0.iter().join("")))
+        f.write_str(self.0)
     }
 }
 
-impl From<[char; 2]> for Node {
-    fn from(value: [char; 2]) -> Self {
+impl<'a> From<&'a str> for Node<'a> {
+    fn from(value: &'a str) -> Self {
         Self(value)
     }
 }
 
-impl TryFrom<Vec<char>> for Node {
-    type Error = <[char; 2] as TryFrom<Vec<char>>>::Error;
-    fn try_from(value: Vec<char>) -> Result<Self, Self::Error> {
-        let array: [char; 2] = value.try_into()?;
-        Ok(Self(array))
-    }
+pub struct Network<'a> {
+    nodes: FxHashSet<Node<'a>>,
+    edges: FxHashMap<Node<'a>, FxHashSet<Node<'a>>>,
 }
 
-struct Network {
-    nodes: FxHashSet<Node>,
-    edges: FxHashMap<Node, FxHashSet<Node>>,
-}
+impl<'a> Network<'a> {
+    fn neighbours(&self, node: &Node<'a>) -> &FxHashSet<Node<'a>> {
+        &self.edges[node]
+    }
+    pub fn degree(&self, node: &Node<'a>) -> usize {
+        self.edges.get(node).map_or(0, |n| n.len())
+    }
+    /// Number of nodes with each degree
+    pub fn degree_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for node in &self.nodes {
+            *histogram.entry(self.degree(node)).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Every set of `k` mutually connected nodes, each sorted, in sorted order
+    pub fn k_cliques(&self, k: usize) -> Vec<Vec<Node<'a>>> {
+        let mut cliques = Vec::new();
+        if k == 0 {
+            return cliques;
+        }
+        let mut stack = self.nodes.iter().sorted().map(|n| vec![*n]).rev().collect_vec();
+        // grow each clique only with nodes after its last member, so every clique is built once in sorted order
+        while let Some(clique) = stack.pop() {
+            if clique.len() == k {
+                cliques.push(clique);
+                continue;
+            }
+            let last = clique.last().unwrap();
+            let candidates = self
+                .neighbours(last)
+                .iter()
+                .filter(|n| *n > last && clique.iter().all(|c| self.neighbours(c).contains(n)))
+                .sorted()
+                .rev();
+            for n in candidates {
+                let mut next = clique.clone();
+                next.push(*n);
+                stack.push(next);
+            }
+        }
+        cliques
+    }
+
+    /// The connected components, each sorted, ordered by their first node
+    pub fn components(&self) -> Vec<Vec<Node<'a>>> {
+        let mut seen = FxHashSet::default();
+        let mut components = Vec::new();
+        for start in self.nodes.iter().sorted() {
+            if !seen.insert(*start) {
+                continue;
+            }
+            let mut component = vec![*start];
+            let mut stack = vec![*start];
+            while let Some(node) = stack.pop() {
+                for n in self.neighbours(&node) {
+                    if seen.insert(*n) {
+                        component.push(*n);
+                        stack.push(*n);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        components
+    }
 
-impl Network {
-    fn groups_3(&self) -> FxHashSet<Vec<Node>> {
-        let mut sets = FxHashSet::default();
-        for n in &self.nodes {
-            let neighbours = self.edges.get(n).unwrap();
-            for neigh in neighbours {
-                let neighbours2 = self.edges.get(neigh).unwrap();
-                for neigh2 in neighbours2 {
-                    let neighbours3 = self.edges.get(neigh2).unwrap();
-                    if neighbours3.contains(n) {
-                        let mut set = vec![*n, *neigh, *neigh2];
-                        set.sort();
-                        sets.insert(set);
+    /// The nodes in degeneracy order: each has the fewest neighbours among the nodes after it, which bounds the
+    /// candidate sets of the outer Bron–Kerbosch loop by the graph's degeneracy
+    pub fn degeneracy_order(&self) -> Vec<Node<'a>> {
+        let mut degrees: FxHashMap<Node, usize> = self.nodes.iter().map(|n| (*n, self.degree(n))).collect();
+        let max_degree = degrees.values().copied().max().unwrap_or(0);
+        let mut buckets = vec![Vec::new(); max_degree + 1];
+        for node in self.nodes.iter().sorted() {
+            buckets[degrees[node]].push(*node);
+        }
+        let mut removed = FxHashSet::default();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while order.len() < self.nodes.len() {
+            // buckets hold stale entries for nodes whose degree has since dropped, skip those
+            let node = buckets
+                .iter_mut()
+                .enumerate()
+                .find_map(|(degree, bucket)| {
+                    while let Some(n) = bucket.pop() {
+                        if !removed.contains(&n) && degrees[&n] == degree {
+                            return Some(n);
+                        }
                     }
+                    None
+                })
+                .unwrap();
+            removed.insert(node);
+            order.push(node);
+            for n in self.neighbours(&node) {
+                if !removed.contains(n) {
+                    let degree = degrees.get_mut(n).unwrap();
+                    *degree -= 1;
+                    buckets[*degree].push(*n);
                 }
             }
         }
-        sets
+        order
     }
+
     // Had to study Wikipedia for this one
     // https://en.wikipedia.org/wiki/Bron%E2%80%93Kerbosch_algorithm
     fn bron_kerbosch(
         &self,
-        r: FxHashSet<Node>,
-        mut p: FxHashSet<Node>,
-        mut x: FxHashSet<Node>,
-    ) -> Vec<FxHashSet<Node>> {
+        r: FxHashSet<Node<'a>>,
+        mut p: FxHashSet<Node<'a>>,
+        mut x: FxHashSet<Node<'a>>,
+    ) -> Vec<FxHashSet<Node<'a>>> {
         let mut results = Vec::new();
         if p.is_empty() && x.is_empty() {
             return vec![r];
@@ -90,31 +172,211 @@ impl Network {
         }
         results
     }
-    fn maximal_subgraphs(&self) -> Vec<FxHashSet<Node>> {
-        self.bron_kerbosch(
-            FxHashSet::default(),
-            FxHashSet::from_iter(self.nodes.iter().copied()),
-            FxHashSet::default(),
-        )
+    /// Every maximal clique, each sorted, in sorted order. The outer loop takes nodes in [Self::degeneracy_order],
+    /// searching only the neighbours that come later and excluding those that came earlier.
+    pub fn maximal_cliques(&self) -> Vec<Vec<Node<'a>>> {
+        let order = self.degeneracy_order();
+        let position: FxHashMap<Node, usize> = order.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let mut cliques = Vec::new();
+        for (i, node) in order.iter().enumerate() {
+            let (later, earlier) = self.neighbours(node).iter().partition(|n| position[*n] > i);
+            let r = FxHashSet::from_iter([*node]);
+            cliques.extend(
+                self.bron_kerbosch(r, later, earlier)
+                    .into_iter()
+                    .map(|clique| clique.into_iter().sorted().collect_vec()),
+            );
+        }
+        cliques.sort();
+        cliques
+    }
+    /// The largest clique, sorted. Ties go to the clique that sorts first.
+    pub fn maximum_clique(&self) -> Vec<Node<'a>> {
+        self.maximal_cliques()
+            .into_iter()
+            .rev()
+            .max_by_key(|clique| clique.len())
+            .unwrap_or_default()
     }
+
+    /// Render the network as an undirected Graphviz graph, with the nodes of `highlight` and the edges between them
+    /// drawn in red
+    pub fn to_dot(&self, highlight: &[Node<'a>]) -> String {
+        let mut dot = String::from("graph network {\n");
+        for node in self.nodes.iter().sorted() {
+            if highlight.contains(node) {
+                dot += &format!("    \"{}\" [style=filled, fillcolor=red];\n", node);
+            } else {
+                dot += &format!("    \"{}\";\n", node);
+            }
+        }
+        for node in self.nodes.iter().sorted() {
+            for n in self.neighbours(node).iter().filter(|n| *n > node).sorted() {
+                if highlight.contains(node) && highlight.contains(n) {
+                    dot += &format!("    \"{}\" -- \"{}\" [color=red, penwidth=2];\n", node, n);
+                } else {
+                    dot += &format!("    \"{}\" -- \"{}\";\n", node, n);
+                }
+            }
+        }
+        dot + "}\n"
+    }
+}
+
+/// A fixed-size set of dense node indices, one bit per node
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+    fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+    fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+    fn intersection(&self, other: &Self) -> Self {
+        Self {
+            words: self.words.iter().zip(&other.words).map(|(a, b)| a & b).collect(),
+        }
+    }
+    fn intersection_len(&self, other: &Self) -> u32 {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones())
+            .sum()
+    }
+    fn difference(&self, other: &Self) -> Self {
+        Self {
+            words: self.words.iter().zip(&other.words).map(|(a, b)| a & !b).collect(),
+        }
+    }
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            words: self.words.iter().zip(&other.words).map(|(a, b)| a | b).collect(),
+        }
+    }
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// A [Network] with its nodes interned as dense indices (in sorted name order) and adjacency stored as bitsets, so
+/// Bron–Kerbosch works on a few machine words per set instead of cloning hash sets
+pub struct BitNetwork<'a> {
+    names: Vec<Node<'a>>,
+    adjacency: Vec<BitSet>,
+    order: Vec<usize>,
 }
 
-impl From<&str> for Network {
-    fn from(input: &str) -> Self {
+impl<'a> From<&Network<'a>> for BitNetwork<'a> {
+    fn from(network: &Network<'a>) -> Self {
+        let names = network.nodes.iter().copied().sorted().collect_vec();
+        let index: FxHashMap<Node, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let adjacency = names
+            .iter()
+            .map(|node| {
+                let mut set = BitSet::new(names.len());
+                for n in network.neighbours(node) {
+                    set.insert(index[n]);
+                }
+                set
+            })
+            .collect();
+        let order = network.degeneracy_order().iter().map(|n| index[n]).collect();
+        Self {
+            names,
+            adjacency,
+            order,
+        }
+    }
+}
+
+impl<'a> BitNetwork<'a> {
+    fn bron_kerbosch(&self, r: &mut Vec<usize>, mut p: BitSet, mut x: BitSet, results: &mut Vec<Vec<usize>>) {
+        if p.is_empty() {
+            if x.is_empty() {
+                results.push(r.clone());
+            }
+            return;
+        }
+        // Tomita's pivot: the candidate with the most neighbours in p, leaving the fewest nodes to branch on. The
+        // hash-set version picks the largest total degree instead, which is cheaper there but can branch more.
+        let pivot = p
+            .union(&x)
+            .iter()
+            .max_by_key(|u| p.intersection_len(&self.adjacency[*u]))
+            .unwrap();
+        for node in p.difference(&self.adjacency[pivot]).iter().collect_vec() {
+            let neighbours = &self.adjacency[node];
+            r.push(node);
+            self.bron_kerbosch(r, p.intersection(neighbours), x.intersection(neighbours), results);
+            r.pop();
+            p.remove(node);
+            x.insert(node);
+        }
+    }
+    /// Every maximal clique, each sorted, in sorted order; identical to [Network::maximal_cliques]
+    pub fn maximal_cliques(&self) -> Vec<Vec<Node<'a>>> {
+        let mut results = Vec::new();
+        let mut earlier = BitSet::new(self.names.len());
+        for node in &self.order {
+            let neighbours = &self.adjacency[*node];
+            let p = neighbours.difference(&earlier);
+            let x = neighbours.intersection(&earlier);
+            self.bron_kerbosch(&mut vec![*node], p, x, &mut results);
+            earlier.insert(*node);
+        }
+        // indices are in name order, so sorting them sorts the names
+        let mut cliques = results
+            .into_iter()
+            .map(|mut clique| {
+                clique.sort();
+                clique.into_iter().map(|i| self.names[i]).collect_vec()
+            })
+            .collect_vec();
+        cliques.sort();
+        cliques
+    }
+    /// The largest clique, sorted; identical to [Network::maximum_clique]
+    pub fn maximum_clique(&self) -> Vec<Node<'a>> {
+        self.maximal_cliques()
+            .into_iter()
+            .rev()
+            .max_by_key(|clique| clique.len())
+            .unwrap_or_default()
+    }
+}
+
+impl<'a> From<&'a str> for Network<'a> {
+    fn from(input: &'a str) -> Self {
         let mut nodes = FxHashSet::default();
         let mut edges = FxHashMap::default();
         for line in input.lines() {
             let (node1, node2) = line.split_once('-').unwrap();
-            let (node1, node2): (Node, Node) = (
-                node1.chars().collect_vec().try_into().unwrap(),
-                node2.chars().collect_vec().try_into().unwrap(),
-            );
-            if !nodes.contains(&node1) {
-                nodes.insert(node1);
-            }
-            if !nodes.contains(&node2) {
-                nodes.insert(node2);
-            }
+            let (node1, node2) = (Node::from(node1), Node::from(node2));
+            nodes.insert(node1);
+            nodes.insert(node2);
             edges.entry(node1).or_insert(FxHashSet::default()).insert(node2);
             edges.entry(node2).or_insert(FxHashSet::default()).insert(node1);
         }
@@ -122,7 +384,7 @@ impl From<&str> for Network {
     }
 }
 
-fn parse(input: &str) -> Network {
+fn parse(input: &str) -> Network<'_> {
     input.into()
 }
 
@@ -130,8 +392,11 @@ fn parse(input: &str) -> Network {
 pub fn part1(input: &str) -> i64 {
     let network = parse(input);
 
-    let sets = network.groups_3();
-    let t_count = sets.iter().filter(|set| set.iter().any(|s| s.0[0] == 't')).count();
+    let sets = network.k_cliques(3);
+    let t_count = sets
+        .iter()
+        .filter(|set| set.iter().any(|s| s.0.starts_with('t')))
+        .count();
 
     t_count as i64
 }
@@ -139,11 +404,13 @@ pub fn part1(input: &str) -> i64 {
 #[aoc(day23, part2)]
 pub fn part2(input: &str) -> String {
     let network = parse(input);
-    let best_sets = network.maximal_subgraphs();
-    let largest_set = best_sets.iter().max_by(|a, b| a.len().cmp(&b.len())).unwrap();
-    let mut largest = largest_set.iter().collect_vec();
-    largest.sort();
-    largest.iter().join(",")
+    network.maximum_clique().iter().join(",")
+}
+
+#[aoc(day23, part2, Bitset)]
+pub fn part2_bitset(input: &str) -> String {
+    let network = parse(input);
+    BitNetwork::from(&network).maximum_clique().iter().join(",")
 }
 
 #[cfg(test)]
@@ -191,4 +458,112 @@ td-yn";
     fn part2_example() {
         assert_eq!(part2(EXAMPLE), "co,de,ka,ta");
     }
+
+    #[test]
+    fn cliques() {
+        let network = parse(EXAMPLE);
+        assert_eq!(network.k_cliques(3).len(), 12);
+        assert_eq!(network.k_cliques(4), [["co", "de", "ka", "ta"].map(Node::from)]);
+        assert!(network.k_cliques(5).is_empty());
+        assert_eq!(network.k_cliques(2).len(), EXAMPLE.lines().count());
+        assert_eq!(network.k_cliques(1).len(), 16);
+
+        let maximal = network.maximal_cliques();
+        assert!(maximal.contains(&["co", "de", "ka", "ta"].map(Node::from).to_vec()));
+        // every triangle is inside some maximal clique
+        for triangle in network.k_cliques(3) {
+            assert!(maximal.iter().any(|clique| triangle.iter().all(|n| clique.contains(n))));
+        }
+    }
+
+    #[test]
+    fn structure() {
+        let network = parse(EXAMPLE);
+        assert_eq!(network.components().len(), 1);
+        assert_eq!(network.degree_histogram(), BTreeMap::from([(4, 16)]));
+
+        let network = parse("alpha-beta\nbeta-gamma\ngamma-alpha\ndelta-epsilon\nalpha-omega");
+        assert_eq!(
+            network.components(),
+            [
+                ["alpha", "beta", "gamma", "omega"].map(Node::from).to_vec(),
+                ["delta", "epsilon"].map(Node::from).to_vec()
+            ]
+        );
+        assert_eq!(network.degree_histogram(), BTreeMap::from([(1, 3), (2, 2), (3, 1)]));
+        assert_eq!(network.maximum_clique(), ["alpha", "beta", "gamma"].map(Node::from));
+
+        // removing nodes in order never leaves one with more later neighbours than the degeneracy (2 here)
+        let order = network.degeneracy_order();
+        assert_eq!(order.len(), 6);
+        for (i, node) in order.iter().enumerate() {
+            let later = network
+                .neighbours(node)
+                .iter()
+                .filter(|n| order[i..].contains(n))
+                .count();
+            assert!(later <= 2);
+        }
+    }
+
+    #[test]
+    fn bitset_example() {
+        assert_eq!(part2_bitset(EXAMPLE), "co,de,ka,ta");
+        let network = parse(EXAMPLE);
+        assert_eq!(BitNetwork::from(&network).maximal_cliques(), network.maximal_cliques());
+
+        let mut set = BitSet::new(130);
+        for i in [0, 63, 64, 129] {
+            set.insert(i);
+        }
+        set.remove(63);
+        assert_eq!(set.iter().collect_vec(), [0, 64, 129]);
+    }
+
+    #[test]
+    fn bitset_matches_hash_set() {
+        // random graphs spanning several bitset words, with names that sort differently from insertion order
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for (nodes, density) in [(10, 2), (70, 4), (150, 8), (200, 20)] {
+            let names = (0..nodes).map(|i| format!("n{}", i * 7919 % 1009)).collect_vec();
+            let mut input = String::new();
+            for i in 0..nodes {
+                for j in i + 1..nodes {
+                    if next() % density == 0 {
+                        input += &format!("{}-{}\n", names[i], names[j]);
+                    }
+                }
+            }
+            let network = parse(&input);
+            let bits = BitNetwork::from(&network);
+            assert_eq!(bits.maximal_cliques(), network.maximal_cliques());
+            assert_eq!(bits.maximum_clique(), network.maximum_clique());
+        }
+    }
+
+    #[test]
+    fn dot() {
+        let network = parse("ab-cd\ncd-ef\nab-ef\nef-gh");
+        let dot = network.to_dot(&network.maximum_clique());
+        assert_eq!(
+            dot,
+            "graph network {
+    \"ab\" [style=filled, fillcolor=red];
+    \"cd\" [style=filled, fillcolor=red];
+    \"ef\" [style=filled, fillcolor=red];
+    \"gh\";
+    \"ab\" -- \"cd\" [color=red, penwidth=2];
+    \"ab\" -- \"ef\" [color=red, penwidth=2];
+    \"cd\" -- \"ef\" [color=red, penwidth=2];
+    \"ef\" -- \"gh\";
+}
+"
+        );
+    }
 }
@@ -1,14 +1,40 @@
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
-use nom::And;
 use regex::Regex;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt::Display;
 
-#[derive(Copy, Clone, Debug)]
+/// Verilog-2005 reserved words, which can't be used as plain identifiers
+const VERILOG_KEYWORDS: &str =
+    "always and assign automatic begin buf bufif0 bufif1 case casex casez cell cmos config deassign default \
+    defparam design disable edge else end endcase endconfig endfunction endgenerate endmodule endprimitive \
+    endspecify endtable endtask event for force forever fork function generate genvar highz0 highz1 if \
+    ifnone incdir include initial inout input instance integer join large liblist library localparam \
+    macromodule medium module nand negedge nmos nor noshowcancelled not notif0 notif1 or output parameter \
+    pmos posedge primitive pull0 pull1 pulldown pullup pulsestyle_ondetect pulsestyle_onevent rcmos real \
+    realtime reg release repeat rnmos rpmos rtran rtranif0 rtranif1 scalared showcancelled signed small \
+    specify specparam strong0 strong1 supply0 supply1 table task time tran tranif0 tranif1 tri tri0 tri1 \
+    triand trior trireg unsigned use uwire vectored wait wand weak0 weak1 while wire wor xnor xor";
+
+/// `name` as a Verilog identifier: as-is when it's a valid simple identifier, otherwise escaped as `\name `
+fn verilog_ident(name: &str) -> String {
+    let simple = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if simple && !VERILOG_KEYWORDS.split_whitespace().any(|k| k == name) {
+        name.to_string()
+    } else {
+        format!("\\{} ", name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum Op {
     And,
     Or,
     Xor,
+    /// A primary input, whose value is set before evaluation
+    Input,
+    /// A wire tied to a fixed value
     Constant,
 }
 
@@ -30,52 +56,799 @@ struct Gate {
     arguments: [String; 2],
 }
 
-impl Gate {
-    fn eval(&self, machine: &GateMachine) -> bool {
-        match self.op {
-            Op::And => machine.val_of(&self.arguments[0]) && machine.val_of(&self.arguments[1]),
-            Op::Or => machine.val_of(&self.arguments[0]) || machine.val_of(&self.arguments[1]),
-            Op::Xor => machine.val_of(&self.arguments[0]) ^ machine.val_of(&self.arguments[1]),
-            Op::Constant => self.value.unwrap(),
+impl Op {
+    fn apply(&self, a: bool, b: bool) -> bool {
+        match self {
+            Op::And => a && b,
+            Op::Or => a || b,
+            Op::Xor => a ^ b,
+            Op::Input | Op::Constant => panic!("inputs and constants have no arguments"),
         }
     }
 }
 
-#[derive(Debug)]
-struct GateMachine {
+impl Gate {
+    /// Whether the gate has a value rather than arguments
+    fn is_source(&self) -> bool {
+        matches!(self.op, Op::Input | Op::Constant)
+    }
+    fn is_input(&self) -> bool {
+        self.arguments
+            .iter()
+            .all(|arg| arg.starts_with('x') || arg.starts_with('y'))
+    }
+    fn is_first_bit(&self) -> bool {
+        self.arguments.iter().all(|arg| arg == "x00" || arg == "y00")
+    }
+}
+
+/// Structural rules of a ripple-carry adder built from full adders:
+///
+/// ```text
+/// x XOR y -> sum        sum XOR carry_in -> z
+/// x AND y -> gen        sum AND carry_in -> prop      gen OR prop -> carry_out
+/// ```
+///
+/// Bit 0 is a half adder (its AND output is the first carry) and the final carry is the most significant `z`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AdderRule {
+    /// Every `z` output except the final carry must come from an XOR
+    OutputNotXor,
+    /// The final carry must come from an OR
+    CarryOutNotOr,
+    /// An XOR that doesn't take `x`/`y` inputs must drive a `z` output
+    InnerXorNotOutput,
+    /// The `x XOR y` half sum must feed the next XOR (except bit 0, which is `z00` itself)
+    HalfSumNotIntoXor,
+    /// AND outputs must feed the carry OR (except bit 0, which is the first carry)
+    AndNotIntoOr,
+}
+
+impl Display for AdderRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AdderRule::OutputNotXor => "z output is not driven by XOR",
+            AdderRule::CarryOutNotOr => "final carry is not driven by OR",
+            AdderRule::InnerXorNotOutput => "XOR of internal wires does not drive a z output",
+            AdderRule::HalfSumNotIntoXor => "x XOR y does not feed an XOR",
+            AdderRule::AndNotIntoOr => "AND does not feed an OR",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GateMachine {
     gates: FxHashMap<String, Gate>,
 }
 
 impl GateMachine {
-    fn val_of(&self, gate: &str) -> bool {
-        println!("gate: {}", gate);
-        if let Some(val) = self.gates[gate].value {
-            val
-        } else {
-            self.gates[gate].eval(self)
+    /// Schedule the gates for evaluation, checking that every wire is driven and there are no cycles. The resulting
+    /// [Netlist] can be run on many `x` and `y` inputs without re-reading the circuit.
+    pub fn compile(&self) -> Result<Netlist, NetlistError> {
+        let names = self.gates.keys().sorted().collect_vec();
+        let index: FxHashMap<&str, usize> = names.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+
+        // Kahn's algorithm: schedule a gate once all its arguments have been
+        let mut pending = vec![0; names.len()];
+        let mut consumers = vec![Vec::new(); names.len()];
+        let mut ready = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let gate = &self.gates[*name];
+            if gate.is_source() {
+                ready.push(i);
+                continue;
+            }
+            for arg in &gate.arguments {
+                let Some(&arg) = index.get(arg.as_str()) else {
+                    return Err(NetlistError::Undriven(arg.clone()));
+                };
+                pending[i] += 1;
+                consumers[arg].push(i);
+            }
+        }
+        let mut schedule = Vec::new();
+        while let Some(i) = ready.pop() {
+            let gate = &self.gates[names[i]];
+            if !gate.is_source() {
+                schedule.push(Step {
+                    op: gate.op,
+                    output: i,
+                    arguments: gate.arguments.clone().map(|arg| index[arg.as_str()]),
+                });
+            }
+            for &consumer in &consumers[i] {
+                pending[consumer] -= 1;
+                if pending[consumer] == 0 {
+                    ready.push(consumer);
+                }
+            }
+        }
+
+        // every unscheduled gate waits on another unscheduled gate, so following those leads round a cycle
+        if let Some(start) = pending.iter().position(|p| *p > 0) {
+            let mut path = vec![start];
+            loop {
+                let gate = &self.gates[names[*path.last().unwrap()]];
+                let next = gate
+                    .arguments
+                    .iter()
+                    .map(|arg| index[arg.as_str()])
+                    .find(|arg| pending[*arg] > 0)
+                    .unwrap();
+                if let Some(pos) = path.iter().position(|i| *i == next) {
+                    return Err(NetlistError::Cycle(
+                        path[pos..].iter().rev().map(|i| names[*i].clone()).collect(),
+                    ));
+                }
+                path.push(next);
+            }
+        }
+
+        let bus = |prefix: char| -> Result<Vec<(u32, usize)>, NetlistError> {
+            names
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| name.starts_with(prefix))
+                .map(|(i, name)| match name[1..].parse::<u32>() {
+                    Ok(bit) if bit < 64 => Ok((bit, i)),
+                    _ => Err(NetlistError::BadBusWire(name.to_string())),
+                })
+                .collect()
+        };
+        Ok(Netlist {
+            initial: names
+                .iter()
+                .map(|name| self.gates[*name].value.unwrap_or(false))
+                .collect(),
+            x: bus('x')?,
+            y: bus('y')?,
+            z: bus('z')?,
+            schedule,
+        })
+    }
+    /// The ops of every gate that takes `wire` as an argument
+    fn consumers(&self) -> FxHashMap<&str, Vec<Op>> {
+        let mut consumers: FxHashMap<&str, Vec<Op>> = FxHashMap::default();
+        for gate in self.gates.values() {
+            for arg in &gate.arguments {
+                consumers.entry(arg).or_default().push(gate.op);
+            }
+        }
+        consumers
+    }
+    /// Check the netlist against the [AdderRule]s, returning each wire that violates one, sorted by wire name
+    pub fn adder_violations(&self) -> Vec<(&str, AdderRule)> {
+        let consumers = self.consumers();
+        let feeds = |wire: &str, op: Op| consumers.get(wire).is_some_and(|ops| ops.contains(&op));
+        let final_carry = self.gates.keys().filter(|wire| wire.starts_with('z')).max();
+
+        let mut violations = Vec::new();
+        for (wire, gate) in self.gates.iter().sorted_by_key(|(wire, _)| *wire) {
+            let rule = match gate.op {
+                Op::Input | Op::Constant => None,
+                Op::Or if Some(wire) == final_carry => None,
+                _ if Some(wire) == final_carry => Some(AdderRule::CarryOutNotOr),
+                Op::And | Op::Or if wire.starts_with('z') => Some(AdderRule::OutputNotXor),
+                Op::Xor if !gate.is_input() && !wire.starts_with('z') => Some(AdderRule::InnerXorNotOutput),
+                Op::Xor if gate.is_input() && !gate.is_first_bit() && !feeds(wire, Op::Xor) => {
+                    Some(AdderRule::HalfSumNotIntoXor)
+                }
+                Op::And if !gate.is_first_bit() && !feeds(wire, Op::Or) => Some(AdderRule::AndNotIntoOr),
+                _ => None,
+            };
+            if let Some(rule) = rule {
+                violations.push((wire.as_str(), rule));
+            }
+        }
+        violations
+    }
+}
+
+/// Why a [GateMachine] can't be evaluated
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NetlistError {
+    /// A wire, used as a gate argument or named in a swap, that no gate or initial value drives
+    Undriven(String),
+    /// Wires that depend on themselves, each driven by a gate taking the previous one as an argument
+    Cycle(Vec<String>),
+    /// An `x`, `y` or `z` wire whose name isn't a bit number below 64
+    BadBusWire(String),
+}
+
+impl Display for NetlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetlistError::Undriven(wire) => write!(f, "wire {} is not driven", wire),
+            NetlistError::Cycle(wires) => write!(f, "cycle {} -> {}", wires.join(" -> "), wires[0]),
+            NetlistError::BadBusWire(wire) => write!(f, "wire {} is not a valid bus bit", wire),
         }
     }
 }
 
-fn parse(input: &str) -> GateMachine {
+/// One gate of a [Netlist] schedule, with wires referred to by index
+#[derive(Clone, Debug)]
+struct Step {
+    op: Op,
+    output: usize,
+    arguments: [usize; 2],
+}
+
+/// A [GateMachine] with its gates topologically sorted, so every wire can be computed in a single pass. Use
+/// [Netlist::run] for one addition, or [Netlist::evaluate_with] and [Netlist::output] to run many of them reusing one
+/// buffer.
+#[derive(Clone, Debug)]
+pub struct Netlist {
+    /// Gates in an order where each one's arguments are computed before it
+    schedule: Vec<Step>,
+    /// Every wire's initial value (false for gate outputs)
+    initial: Vec<bool>,
+    /// (bit, wire index) of the `x`, `y` and `z` buses
+    x: Vec<(u32, usize)>,
+    y: Vec<(u32, usize)>,
+    z: Vec<(u32, usize)>,
+}
+
+impl Netlist {
+    /// Compute every gate output in `values`, which is indexed by wire and holds the inputs
+    fn evaluate(&self, values: &mut [bool]) {
+        for step in &self.schedule {
+            values[step.output] = step.op.apply(values[step.arguments[0]], values[step.arguments[1]]);
+        }
+    }
+    fn load(values: &mut [bool], bus: &[(u32, usize)], val: u64) {
+        for (bit, wire) in bus {
+            values[*wire] = val & (1 << bit) != 0;
+        }
+    }
+    fn read(values: &[bool], bus: &[(u32, usize)]) -> u64 {
+        bus.iter()
+            .filter(|(_, wire)| values[*wire])
+            .fold(0, |accum, (bit, _)| accum | (1 << bit))
+    }
+    /// The number of bits in `bus`, if they're numbered contiguously from 0
+    fn width(bus: &[(u32, usize)]) -> Option<u32> {
+        let mut bits = bus.iter().map(|(bit, _)| *bit).sorted();
+        bits.by_ref()
+            .enumerate()
+            .all(|(i, bit)| i as u32 == bit)
+            .then_some(bus.len() as u32)
+    }
+    /// The initial values of the `x` and `y` inputs
+    pub fn inputs(&self) -> (u64, u64) {
+        (Self::read(&self.initial, &self.x), Self::read(&self.initial, &self.y))
+    }
+    /// Every wire's value with `x` and `y` as inputs, reusing `values` (which is resized to fit). Wires missing from
+    /// a bus keep their initial value.
+    pub fn evaluate_with(&self, x: u64, y: u64, values: &mut Vec<bool>) {
+        values.clone_from(&self.initial);
+        Self::load(values, &self.x, x);
+        Self::load(values, &self.y, y);
+        self.evaluate(values);
+    }
+    /// The `z` output of wire values computed by [Netlist::evaluate_with]
+    pub fn output(&self, values: &[bool]) -> u64 {
+        Self::read(values, &self.z)
+    }
+    /// The `z` output with `x` and `y` as inputs
+    pub fn run(&self, x: u64, y: u64) -> u64 {
+        let mut values = Vec::with_capacity(self.initial.len());
+        self.evaluate_with(x, y, &mut values);
+        Self::read(&values, &self.z)
+    }
+}
+
+/// A BLIF line that couldn't be read into a [GateMachine]. `line` is 1-based.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlifError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for BlifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for BlifError {}
+
+impl GateMachine {
+    /// Gates sorted by output wire, for stable output
+    fn sorted_gates(&self) -> impl Iterator<Item = (&String, &Gate)> {
+        self.gates.iter().sorted_by_key(|(wire, _)| *wire)
+    }
+    fn inputs(&self) -> impl Iterator<Item = &String> {
+        self.sorted_gates()
+            .filter(|(_, g)| g.op == Op::Input)
+            .map(|(wire, _)| wire)
+    }
+    fn outputs(&self) -> impl Iterator<Item = &String> {
+        self.gates.keys().filter(|wire| wire.starts_with('z')).sorted()
+    }
+
+    /// Render the circuit as a Graphviz graph flowing left to right, with the `x`/`y` inputs in the first rank, the
+    /// `z` outputs in the last, and gates coloured by operation
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n    node [style=filled];\n");
+        let rank = |rank: &str, wires: Vec<&String>| {
+            format!(
+                "    {{ rank={}; {} }}\n",
+                rank,
+                wires.iter().map(|w| format!("\"{}\";", w)).join(" ")
+            )
+        };
+        dot += &rank(
+            "source",
+            self.inputs()
+                .filter(|w| w.starts_with('x') || w.starts_with('y'))
+                .collect(),
+        );
+        dot += &rank("sink", self.outputs().collect());
+        for (wire, gate) in self.sorted_gates() {
+            let (label, colour) = match gate.op {
+                Op::And => ("AND", "lightblue"),
+                Op::Or => ("OR", "palegreen"),
+                Op::Xor => ("XOR", "orange"),
+                Op::Input | Op::Constant => {
+                    dot += &format!("    \"{}\" [shape=box, fillcolor=lightgrey];\n", wire);
+                    continue;
+                }
+            };
+            let periphery = if wire.starts_with('z') { ", peripheries=2" } else { "" };
+            dot += &format!(
+                "    \"{}\" [label=\"{}\\n{}\", fillcolor={}{}];\n",
+                wire, wire, label, colour, periphery
+            );
+            for arg in &gate.arguments {
+                dot += &format!("    \"{}\" -> \"{}\";\n", arg, wire);
+            }
+        }
+        dot + "}\n"
+    }
+
+    /// Render the circuit as a structural Verilog module of gate primitives. Initial input values are not included.
+    pub fn to_verilog(&self, module: &str) -> String {
+        let inputs = self.inputs().collect_vec();
+        let outputs = self.outputs().collect_vec();
+        let wires = self
+            .sorted_gates()
+            .filter(|(wire, gate)| gate.op != Op::Input && !wire.starts_with('z'))
+            .map(|(wire, _)| wire)
+            .collect_vec();
+        let mut verilog = format!(
+            "module {}({});\n",
+            verilog_ident(module),
+            inputs.iter().chain(&outputs).map(|w| verilog_ident(w)).join(", ")
+        );
+        for (kind, wires) in [("input", &inputs), ("output", &outputs), ("wire", &wires)] {
+            if !wires.is_empty() {
+                verilog += &format!("    {} {};\n", kind, wires.iter().map(|w| verilog_ident(w)).join(", "));
+            }
+        }
+        for (wire, gate) in self.sorted_gates() {
+            let primitive = match gate.op {
+                Op::And => "and",
+                Op::Or => "or",
+                Op::Xor => "xor",
+                Op::Input => continue,
+                Op::Constant => {
+                    verilog += &format!(
+                        "    assign {} = 1'b{};\n",
+                        verilog_ident(wire),
+                        (gate.value == Some(true)) as u8
+                    );
+                    continue;
+                }
+            };
+            verilog += &format!(
+                "    {} {}({}, {}, {});\n",
+                primitive,
+                verilog_ident(&format!("g_{}", wire)),
+                verilog_ident(wire),
+                verilog_ident(&gate.arguments[0]),
+                verilog_ident(&gate.arguments[1])
+            );
+        }
+        verilog + "endmodule\n"
+    }
+
+    /// Render the circuit as a BLIF model, one `.names` cover per gate or constant. Initial input values are not
+    /// included.
+    pub fn to_blif(&self, model: &str) -> String {
+        let mut blif = format!(".model {}\n", model);
+        for (kind, wires) in [
+            (".inputs", self.inputs().collect_vec()),
+            (".outputs", self.outputs().collect_vec()),
+        ] {
+            if !wires.is_empty() {
+                blif += &format!("{} {}\n", kind, wires.iter().join(" "));
+            }
+        }
+        for (wire, gate) in self.sorted_gates() {
+            let cover = match gate.op {
+                Op::And => "11 1\n",
+                Op::Or => "1- 1\n-1 1\n",
+                Op::Xor => "10 1\n01 1\n",
+                Op::Input => continue,
+                // an empty cover is constant 0
+                Op::Constant => {
+                    blif += &format!(".names {}\n{}", wire, if gate.value == Some(true) { "1\n" } else { "" });
+                    continue;
+                }
+            };
+            blif += &format!(".names {} {} {}\n{}", gate.arguments[0], gate.arguments[1], wire, cover);
+        }
+        blif + ".end\n"
+    }
+
+    /// Read a combinational BLIF model. Each `.names` block must compute AND, OR or XOR of two wires, or be a
+    /// constant, and its cover may list either the on-set or the off-set. `.inputs` become inputs with value 0.
+    pub fn from_blif(blif: &str) -> Result<GateMachine, BlifError> {
+        // join `\` continuations, keeping the number of each logical line's first physical line
+        let mut lines = Vec::new();
+        let mut pending: Option<(usize, String)> = None;
+        for (i, line) in blif.lines().enumerate() {
+            let line = line.split('#').next().unwrap();
+            let (start, mut text) = pending.take().unwrap_or((i + 1, String::new()));
+            match line.trim_end().strip_suffix('\\') {
+                Some(part) => {
+                    text += part;
+                    text += " ";
+                    pending = Some((start, text));
+                }
+                None => {
+                    text += line;
+                    if !text.trim().is_empty() {
+                        lines.push((start, text));
+                    }
+                }
+            }
+        }
+        if let Some((start, text)) = pending {
+            lines.push((start, text));
+        }
+
+        let mut gates = FxHashMap::default();
+        let mut outputs: Vec<(usize, String)> = Vec::new();
+        let mut lines = lines.iter().peekable();
+        while let Some((line, text)) = lines.next() {
+            let err = |message: String| BlifError { line: *line, message };
+            let mut tokens = text.split_whitespace();
+            let directive = tokens.next().unwrap();
+            let mut insert = |wire: &str, gate: Gate| match gates.insert(wire.to_string(), gate) {
+                Some(_) => Err(err(format!("wire {} is driven twice", wire))),
+                None => Ok(()),
+            };
+            match directive {
+                ".model" => {}
+                ".inputs" => {
+                    for wire in tokens {
+                        insert(
+                            wire,
+                            Gate {
+                                op: Op::Input,
+                                value: Some(false),
+                                arguments: [String::new(), String::new()],
+                            },
+                        )?;
+                    }
+                }
+                ".outputs" => outputs.extend(tokens.map(|wire| (*line, wire.to_string()))),
+                ".names" => {
+                    let wires = tokens.collect_vec();
+                    let Some((output, args)) = wires.split_last() else {
+                        return Err(err(".names needs an output wire".to_string()));
+                    };
+                    let mut rows = Vec::new();
+                    while let Some((row_line, row)) = lines.next_if(|(_, text)| !text.trim_start().starts_with('.')) {
+                        rows.push((*row_line, row.split_whitespace().collect_vec()));
+                    }
+                    let gate = Self::cover_gate(args, &rows).map_err(err)?;
+                    insert(output, gate)?;
+                }
+                ".end" => break,
+                d => return Err(err(format!("unsupported directive {}", d))),
+            }
+        }
+        for (line, wire) in outputs {
+            if !gates.contains_key(&wire) {
+                return Err(BlifError {
+                    line,
+                    message: format!("output {} is not driven", wire),
+                });
+            }
+        }
+        Ok(GateMachine { gates })
+    }
+
+    /// The gate computing the function of `args` given by a `.names` cover
+    fn cover_gate(args: &[&str], rows: &[(usize, Vec<&str>)]) -> Result<Gate, String> {
+        let mut on_set = None;
+        let mut table = [false; 4];
+        for (line, row) in rows {
+            let (pattern, output) = match (args.len(), &row[..]) {
+                (0, [output]) => ("", *output),
+                (_, [pattern, output]) if pattern.len() == args.len() => (*pattern, *output),
+                _ => return Err(format!("malformed cover row on line {}", line)),
+            };
+            let value = match output {
+                "1" => true,
+                "0" => false,
+                o => return Err(format!("invalid cover output {} on line {}", o, line)),
+            };
+            if *on_set.get_or_insert(value) != value {
+                return Err(format!("cover mixes on-set and off-set rows on line {}", line));
+            }
+            if !pattern.chars().all(|c| matches!(c, '0' | '1' | '-')) {
+                return Err(format!("invalid cover pattern {} on line {}", pattern, line));
+            }
+            for (i, entry) in table.iter_mut().enumerate() {
+                let bits = [i & 2 != 0, i & 1 != 0];
+                if pattern.chars().zip(bits).all(|(c, bit)| c == '-' || (c == '1') == bit) {
+                    *entry = true;
+                }
+            }
+        }
+        // rows list where the output is `on_set`, the rest of the table has the other value
+        if on_set == Some(false) {
+            table = table.map(|v| !v);
+        }
+        match args {
+            [] => Ok(Gate {
+                op: Op::Constant,
+                value: Some(table[0]),
+                arguments: [String::new(), String::new()],
+            }),
+            [a, b] => {
+                let op = match table {
+                    [false, false, false, true] => Op::And,
+                    [false, true, true, true] => Op::Or,
+                    [false, true, true, false] => Op::Xor,
+                    _ => return Err(format!("{} {} is not an AND, OR or XOR", a, b)),
+                };
+                Ok(Gate {
+                    op,
+                    value: None,
+                    arguments: [a.to_string(), b.to_string()],
+                })
+            }
+            _ => Err(format!("gates must have 2 inputs, not {}", args.len())),
+        }
+    }
+}
+
+/// Why [GateMachine::check_adder] rejected a circuit
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdderFailure {
+    /// The circuit can't be evaluated
+    Invalid(NetlistError),
+    /// `x` and `y` must have the same number of bits (at most 63) and `z` one more, all numbered from 0
+    BusWidths { x: usize, y: usize, z: usize },
+    /// `x + y` gave the wrong result. `bit` is the lowest wrong `z` bit over every case tried and `cone` the gates it
+    /// depends on, sorted by wire name.
+    WrongSum {
+        x: u64,
+        y: u64,
+        expected: u64,
+        actual: u64,
+        bit: u32,
+        cone: Vec<String>,
+    },
+}
+
+impl Display for AdderFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdderFailure::Invalid(e) => write!(f, "invalid circuit: {}", e),
+            AdderFailure::BusWidths { x, y, z } => write!(f, "bus widths x={} y={} z={} do not form an adder", x, y, z),
+            AdderFailure::WrongSum {
+                x,
+                y,
+                expected,
+                actual,
+                bit,
+                cone,
+            } => write!(
+                f,
+                "{} + {} gave {} instead of {}, first wrong at z{:02} (driven by {})",
+                x,
+                y,
+                actual,
+                expected,
+                bit,
+                cone.join(",")
+            ),
+        }
+    }
+}
+
+/// Why [GateMachine::repair_adder] couldn't find the swapped wires
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RepairError {
+    /// The [AdderRule]s flagged a different number of wires than the swaps account for
+    Flagged { expected: usize, wires: Vec<String> },
+    /// No way of pairing up the flagged wires makes the circuit pass [GateMachine::check_adder]
+    Unconfirmed { wires: Vec<String> },
+}
+
+impl Display for RepairError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairError::Flagged { expected, wires } => write!(
+                f,
+                "expected {} suspicious wires, found {} ({})",
+                expected,
+                wires.len(),
+                wires.join(",")
+            ),
+            RepairError::Unconfirmed { wires } => {
+                write!(f, "no swaps among {} make the circuit add correctly", wires.join(","))
+            }
+        }
+    }
+}
+
+/// splitmix64, for reproducible test operands without an RNG dependency
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl GateMachine {
+    /// Swap the outputs of the gates driving wires `a` and `b`. Fails without changing anything if either wire isn't
+    /// driven.
+    pub fn swap_outputs(&mut self, a: &str, b: &str) -> Result<(), NetlistError> {
+        for wire in [a, b] {
+            if !self.gates.contains_key(wire) {
+                return Err(NetlistError::Undriven(wire.to_string()));
+            }
+        }
+        if a != b {
+            let gate_a = self.gates.remove(a).unwrap();
+            let gate_b = self.gates.insert(b.to_string(), gate_a).unwrap();
+            self.gates.insert(a.to_string(), gate_b);
+        }
+        Ok(())
+    }
+
+    /// Find the `pairs` swapped pairs of gate outputs that stop the circuit being an adder. The wires breaking an
+    /// [AdderRule] are taken as the suspects, and a pairing of them only counts once swapping each pair makes the
+    /// circuit pass [GateMachine::check_adder]. Returns the pairs in the order they were tried.
+    pub fn repair_adder(&self, pairs: usize) -> Result<Vec<(String, String)>, RepairError> {
+        let wires = self
+            .adder_violations()
+            .into_iter()
+            .map(|(wire, _)| wire.to_string())
+            .collect_vec();
+        if wires.len() != 2 * pairs {
+            return Err(RepairError::Flagged {
+                expected: 2 * pairs,
+                wires,
+            });
+        }
+        self.confirm_pairing(&wires, &mut Vec::new())
+            .ok_or(RepairError::Unconfirmed { wires })
+    }
+    /// Pair the first of `unpaired` with each of the others in turn, recursing until a complete pairing adds correctly
+    fn confirm_pairing(&self, unpaired: &[String], pairs: &mut Vec<(String, String)>) -> Option<Vec<(String, String)>> {
+        let Some((first, rest)) = unpaired.split_first() else {
+            let mut repaired = self.clone();
+            for (a, b) in pairs.iter() {
+                repaired.swap_outputs(a, b).ok()?;
+            }
+            return repaired.check_adder(100, 1).is_ok().then(|| pairs.clone());
+        };
+        for (i, partner) in rest.iter().enumerate() {
+            let others = rest
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, w)| w.clone())
+                .collect_vec();
+            pairs.push((first.clone(), partner.clone()));
+            let found = self.confirm_pairing(&others, pairs);
+            pairs.pop();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Every gate that `wire` depends on, including its own, sorted by wire name
+    pub fn cone_of(&self, wire: &str) -> Vec<String> {
+        let mut cone = Vec::new();
+        let mut seen = FxHashSet::default();
+        let mut stack = vec![wire];
+        while let Some(wire) = stack.pop() {
+            match self.gates.get(wire) {
+                Some(gate) if !gate.is_source() && seen.insert(wire) => {
+                    cone.push(wire.to_string());
+                    stack.extend(gate.arguments.iter().map(|arg| arg.as_str()));
+                }
+                _ => {}
+            }
+        }
+        cone.sort();
+        cone
+    }
+
+    /// Check that the circuit computes `z = x + y` for edge cases (zero, all ones, a carry into and out of each
+    /// bit) and `random_cases` random operands generated from `seed`. Returns the number of cases checked.
+    pub fn check_adder(&self, random_cases: usize, seed: u64) -> Result<usize, AdderFailure> {
+        let netlist = self.compile().map_err(AdderFailure::Invalid)?;
+        let bits = match (
+            Netlist::width(&netlist.x),
+            Netlist::width(&netlist.y),
+            Netlist::width(&netlist.z),
+        ) {
+            (Some(x), Some(y), Some(z)) if x == y && z == x + 1 && x < 64 => x,
+            _ => {
+                return Err(AdderFailure::BusWidths {
+                    x: netlist.x.len(),
+                    y: netlist.y.len(),
+                    z: netlist.z.len(),
+                })
+            }
+        };
+        let mask = (1u64 << bits) - 1;
+
+        let mut cases = vec![(0, 0), (mask, mask), (mask, 1), (1, mask)];
+        for bit in 0..bits {
+            let one = 1 << bit;
+            cases.extend([(one, 0), (0, one), (one, one), (one - 1, 1), (mask, one)]);
+        }
+        let mut state = seed;
+        cases.extend((0..random_cases).map(|_| (splitmix64(&mut state) & mask, splitmix64(&mut state) & mask)));
+
+        let mut values = Vec::with_capacity(netlist.initial.len());
+        let mut lowest: Option<(u32, u64, u64, u64)> = None;
+        for (x, y) in &cases {
+            netlist.evaluate_with(*x, *y, &mut values);
+            let actual = Netlist::read(&values, &netlist.z);
+            let wrong = actual ^ (x + y);
+            if wrong != 0 && lowest.is_none_or(|(bit, ..)| wrong.trailing_zeros() < bit) {
+                lowest = Some((wrong.trailing_zeros(), *x, *y, actual));
+            }
+        }
+        match lowest {
+            None => Ok(cases.len()),
+            Some((bit, x, y, actual)) => Err(AdderFailure::WrongSum {
+                x,
+                y,
+                expected: x + y,
+                actual,
+                bit,
+                cone: self.cone_of(
+                    self.gates
+                        .keys()
+                        .find(|wire| wire.starts_with('z') && wire[1..].parse() == Ok(bit))
+                        .unwrap(),
+                ),
+            }),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> GateMachine {
+    let const_re = Regex::new(r"^([xyz][0-9]{2}): ([01])$").unwrap();
+    let gate_re = Regex::new(r"^([a-z0-9]{3}) (AND|XOR|OR) ([a-z0-9]{3}) -> ([a-z0-9]{3})$").unwrap();
+
     let mut gates = FxHashMap::default();
     for line in input.lines() {
-        println!("{line}");
-        let const_re = Regex::new(r"^([xyz][0-9]{2}): ([01])$").unwrap();
-        let gate_re = Regex::new(r"^([a-z0-9]{3}) (AND|XOR|OR) ([a-z0-9]{3}) -> ([a-z0-9]{3})$").unwrap();
-
         if let Some(caps) = const_re.captures(line) {
-            println!(" is const: {:?}", caps);
             gates.insert(
                 caps[1].to_string(),
                 Gate {
-                    op: Op::Constant,
+                    op: Op::Input,
                     value: if &caps[2] == "1" { Some(true) } else { Some(false) },
                     arguments: [String::new(), String::new()],
                 },
             );
         } else if let Some(caps) = gate_re.captures(line) {
-            println!(" is gate: {:?}", caps);
             gates.insert(
                 caps[4].to_string(),
                 Gate {
@@ -91,21 +864,36 @@ fn parse(input: &str) -> GateMachine {
 
 #[aoc(day24, part1)]
 pub fn part1(input: &str) -> i64 {
-    let machine = parse(input);
-    let z_gates = machine
-        .gates
-        .keys()
-        .filter(|k| k.starts_with('z'))
-        .map(|s| (s, s.split_at(1).1.parse::<usize>().unwrap()));
-    let bit_vals = z_gates
-        .map(|(name, bit)| if machine.val_of(name) { 1 << bit } else { 0 })
-        .fold(0, |accum, val| accum | val);
-    bit_vals
+    let netlist = parse(input)
+        .compile()
+        .unwrap_or_else(|e| panic!("invalid circuit: {}", e));
+    let (x, y) = netlist.inputs();
+    netlist.run(x, y) as i64
 }
 
 #[aoc(day24, part2)]
-pub fn part2(input: &str) -> i64 {
-    0
+pub fn part2(input: &str) -> String {
+    part2_impl(input, 4)
+}
+
+/// The wires of the `pairs` swapped pairs, sorted and comma-joined
+pub fn part2_impl(input: &str, pairs: usize) -> String {
+    let machine = parse(input);
+    let swaps = machine
+        .repair_adder(pairs)
+        .unwrap_or_else(|e| panic!("can't repair the adder: {}", e));
+    swaps.into_iter().flat_map(|(a, b)| [a, b]).sorted().join(",")
+}
+
+/// The part 2 answer with the rule each wire broke, one wire per line, for auditing
+#[aoc(day24, part2, Audit)]
+pub fn part2_audit(input: &str) -> String {
+    let machine = parse(input);
+    machine
+        .adder_violations()
+        .iter()
+        .map(|(wire, rule)| format!("{}: {}", wire, rule))
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -119,53 +907,7 @@ y01: 1
 y02: 0
 
 x00 AND y00 -> z00
-x01 XOR y01 -> z01x00: 1
-x01: 0
-x02: 1
-x03: 1
-x04: 0
-y00: 1
-y01: 1
-y02: 1
-y03: 1
-y04: 1
-
-ntg XOR fgs -> mjb
-y02 OR x01 -> tnw
-kwq OR kpj -> z05
-x00 OR x03 -> fst
-tgd XOR rvg -> z01
-vdt OR tnw -> bfw
-bfw AND frj -> z10
-ffh OR nrd -> bqk
-y00 AND y03 -> djm
-y03 OR y00 -> psh
-bqk OR frj -> z08
-tnw OR fst -> frj
-gnj AND tgd -> z11
-bfw XOR mjb -> z00
-x03 OR x00 -> vdt
-gnj AND wpb -> z02
-x04 AND y00 -> kjc
-djm OR pbm -> qhw
-nrd AND vdt -> hwm
-kjc AND fst -> rvg
-y04 OR y02 -> fgs
-y01 AND x02 -> pbm
-ntg OR kjc -> kwq
-psh XOR fgs -> tgd
-qhw XOR tgd -> z09
-pbm OR djm -> kpj
-x03 XOR y03 -> ffh
-x00 XOR y04 -> ntg
-bfw OR bqk -> z06
-nrd XOR fgs -> wpb
-frj XOR qhw -> z04
-bqk OR frj -> z07
-y03 OR x01 -> nrd
-hwm AND bqk -> z03
-tgd XOR rvg -> z12
-tnw OR pbm -> gnj
+x01 XOR y01 -> z01
 x02 OR y02 -> z02";
 
     const EXAMPLE2: &str = "x00: 1
@@ -222,8 +964,340 @@ tnw OR pbm -> gnj";
         assert_eq!(part1(EXAMPLE2), 2024);
     }
 
+    // 4 bit ripple-carry adder, with z02/cr2 and hs3/gn3 swapped
+    const ADDER: &str = "x00: 0
+x01: 0
+x02: 0
+x03: 0
+y00: 0
+y01: 0
+y02: 0
+y03: 0
+
+x00 XOR y00 -> z00
+x00 AND y00 -> cr0
+x01 XOR y01 -> hs1
+x01 AND y01 -> gn1
+hs1 XOR cr0 -> z01
+hs1 AND cr0 -> pr1
+gn1 OR pr1 -> cr1
+x02 XOR y02 -> hs2
+x02 AND y02 -> gn2
+hs2 XOR cr1 -> cr2
+hs2 AND cr1 -> pr2
+gn2 OR pr2 -> z02
+x03 XOR y03 -> gn3
+x03 AND y03 -> hs3
+hs3 XOR cr2 -> z03
+hs3 AND cr2 -> pr3
+gn3 OR pr3 -> z04";
+
     #[test]
     fn part2_example() {
-        assert_eq!(part2(EXAMPLE1), 0);
+        assert_eq!(part2_impl(ADDER, 2), "cr2,gn3,hs3,z02");
+        let machine = parse(ADDER);
+        assert_eq!(
+            machine.repair_adder(2),
+            Ok(vec![
+                ("cr2".to_string(), "z02".to_string()),
+                ("gn3".to_string(), "hs3".to_string())
+            ])
+        );
+        let Err(RepairError::Flagged { expected: 8, wires }) = machine.repair_adder(4) else {
+            panic!("expected the wrong number of suspects");
+        };
+        assert_eq!(wires.len(), 4);
+
+        // gn1 and gn2 swapped as well breaks the sum without breaking any rule
+        let mut machine = parse(ADDER);
+        machine.swap_outputs("gn1", "gn2").unwrap();
+        let err = machine.repair_adder(2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no swaps among cr2,gn3,hs3,z02 make the circuit add correctly"
+        );
+        assert_eq!(
+            parse(ADDER).adder_violations(),
+            [
+                ("cr2", AdderRule::InnerXorNotOutput),
+                ("gn3", AdderRule::HalfSumNotIntoXor),
+                ("hs3", AdderRule::AndNotIntoOr),
+                ("z02", AdderRule::OutputNotXor),
+            ]
+        );
+        assert_eq!(
+            part2_audit(ADDER),
+            "cr2: XOR of internal wires does not drive a z output
+gn3: x XOR y does not feed an XOR
+hs3: AND does not feed an OR
+z02: z output is not driven by XOR"
+        );
+    }
+
+    #[test]
+    fn netlist_evaluation() {
+        let swapped = parse(ADDER).compile().unwrap();
+        let fixed = ADDER
+            .replace("-> z02", "-> tmp")
+            .replace("-> cr2", "-> z02")
+            .replace("-> tmp", "-> cr2")
+            .replace("-> gn3", "-> tmp")
+            .replace("-> hs3", "-> gn3")
+            .replace("-> tmp", "-> hs3");
+        let fixed = parse(&fixed).compile().unwrap();
+        let mut values = Vec::new();
+        for x in 0..16 {
+            for y in 0..16 {
+                assert_eq!(fixed.run(x, y), x + y);
+                fixed.evaluate_with(x, y, &mut values);
+                assert_eq!(Netlist::read(&values, &fixed.z), x + y);
+            }
+        }
+        assert_eq!(swapped.run(4, 4), 4);
+        assert_eq!(parse(EXAMPLE2).compile().unwrap().inputs(), (0b01101, 0b11111));
+    }
+
+    #[test]
+    fn swap_outputs() {
+        let mut machine = parse(ADDER);
+        machine.swap_outputs("z02", "cr2").unwrap();
+        machine.swap_outputs("gn3", "hs3").unwrap();
+        let fixed = ADDER
+            .replace("-> z02", "-> tmp")
+            .replace("-> cr2", "-> z02")
+            .replace("-> tmp", "-> cr2")
+            .replace("-> gn3", "-> tmp")
+            .replace("-> hs3", "-> gn3")
+            .replace("-> tmp", "-> hs3");
+        assert_eq!(structure(&machine), structure(&parse(&fixed)));
+        assert_eq!(machine.compile().unwrap().run(4, 4), 8);
+
+        // swapping back restores the original
+        machine.swap_outputs("cr2", "z02").unwrap();
+        machine.swap_outputs("hs3", "gn3").unwrap();
+        assert_eq!(structure(&machine), structure(&parse(ADDER)));
+    }
+
+    #[test]
+    fn swap_outputs_unknown_wire() {
+        let mut machine = parse(ADDER);
+        for (a, b) in [("z02", "qqq"), ("qqq", "z02")] {
+            assert_eq!(
+                machine.swap_outputs(a, b),
+                Err(NetlistError::Undriven("qqq".to_string()))
+            );
+            assert_eq!(structure(&machine), structure(&parse(ADDER)));
+        }
+        // a wire swapped with itself is left alone
+        assert_eq!(machine.swap_outputs("z02", "z02"), Ok(()));
+        assert_eq!(structure(&machine), structure(&parse(ADDER)));
+    }
+
+    #[test]
+    fn netlist_reuses_buffer() {
+        let netlist = parse(ADDER).compile().unwrap();
+        let mut values = Vec::new();
+        for (x, y) in (0..16).cartesian_product(0..16) {
+            netlist.evaluate_with(x, y, &mut values);
+            assert_eq!(netlist.output(&values), netlist.run(x, y));
+        }
+        assert_eq!(netlist.inputs(), (0, 0));
+    }
+
+    #[test]
+    fn netlist_errors() {
+        let cycle = "x00: 1
+y00: 0
+
+x00 AND abc -> def
+def OR y00 -> ghi
+ghi XOR x00 -> abc
+abc XOR x00 -> z00";
+        let Err(NetlistError::Cycle(wires)) = parse(cycle).compile() else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(wires.iter().sorted().collect_vec(), ["abc", "def", "ghi"]);
+        let pos = wires.iter().position(|w| w == "abc").unwrap();
+        assert_eq!(wires[(pos + 1) % 3], "def");
+
+        assert_eq!(
+            parse("x00: 1\n\nx00 AND qqq -> z00").compile().unwrap_err(),
+            NetlistError::Undriven("qqq".to_string())
+        );
+        assert_eq!(
+            parse("x00: 1\n\nx00 AND x00 -> zab").compile().unwrap_err().to_string(),
+            "wire zab is not a valid bus bit"
+        );
+    }
+
+    fn structure(machine: &GateMachine) -> Vec<(&String, Op, &[String; 2])> {
+        machine
+            .sorted_gates()
+            .map(|(wire, gate)| (wire, gate.op, &gate.arguments))
+            .collect()
+    }
+
+    #[test]
+    fn export() {
+        let machine = parse(EXAMPLE1);
+        let dot = machine.to_dot();
+        assert!(dot.starts_with("digraph circuit {\n    rankdir=LR;\n"));
+        assert!(dot.contains("    { rank=source; \"x00\"; \"x01\"; \"x02\"; \"y00\"; \"y01\"; \"y02\"; }\n"));
+        assert!(dot.contains("    { rank=sink; \"z00\"; \"z01\"; \"z02\"; }\n"));
+        assert!(dot.contains("    \"z01\" [label=\"z01\\nXOR\", fillcolor=orange, peripheries=2];\n"));
+        assert!(dot.contains("    \"y01\" -> \"z01\";\n"));
+
+        assert_eq!(
+            machine.to_verilog("example"),
+            "module example(x00, x01, x02, y00, y01, y02, z00, z01, z02);
+    input x00, x01, x02, y00, y01, y02;
+    output z00, z01, z02;
+    and g_z00(z00, x00, y00);
+    xor g_z01(z01, x01, y01);
+    or g_z02(z02, x02, y02);
+endmodule
+"
+        );
+        assert_eq!(
+            machine.to_blif("example"),
+            ".model example
+.inputs x00 x01 x02 y00 y01 y02
+.outputs z00 z01 z02
+.names x00 y00 z00
+11 1
+.names x01 y01 z01
+10 1
+01 1
+.names x02 y02 z02
+1- 1
+-1 1
+.end
+"
+        );
+        assert!(parse(ADDER).to_verilog("adder").contains("    wire cr0, cr1, cr2, gn1"));
+
+        let keywords = parse("x00: 1\ny00: 0\n\nx00 AND y00 -> and\nand XOR x00 -> xor\nxor OR and -> z00");
+        assert_eq!(
+            keywords.to_verilog("module"),
+            "module \\module (x00, y00, z00);
+    input x00, y00;
+    output z00;
+    wire \\and , \\xor ;
+    and g_and(\\and , x00, y00);
+    xor g_xor(\\xor , \\and , x00);
+    or g_z00(z00, \\xor , \\and );
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn blif_round_trip() {
+        let machine = parse(ADDER);
+        let parsed = GateMachine::from_blif(&machine.to_blif("adder")).unwrap();
+        assert_eq!(structure(&parsed), structure(&machine));
+        assert_eq!(parsed.to_blif("adder"), machine.to_blif("adder"));
+
+        // other ways of writing the same gates
+        let blif = "# comment
+.model alt
+.inputs a b \\
+    c
+.outputs z00
+.names a b ab   # off-set AND
+0- 0
+-0 0
+.names ab c \\
+    z00
+00 0
+.names one
+1
+.end";
+        let parsed = GateMachine::from_blif(blif).unwrap();
+        assert_eq!(parsed.gates["ab"].op, Op::And);
+        assert_eq!(parsed.gates["z00"].op, Op::Or);
+        assert_eq!(parsed.gates["z00"].arguments, ["ab".to_string(), "c".to_string()]);
+        assert_eq!(parsed.gates["one"].value, Some(true));
+        assert_eq!(parsed.gates["c"].value, Some(false));
+
+        // constants stay constants rather than becoming inputs
+        let exported = parsed.to_blif("alt");
+        assert!(exported.starts_with(".model alt\n.inputs a b c\n.outputs z00\n"));
+        assert!(exported.contains(".names one\n1\n"));
+        let reparsed = GateMachine::from_blif(&exported).unwrap();
+        assert_eq!(reparsed.to_blif("alt"), exported);
+        assert_eq!(reparsed.gates["one"].op, Op::Constant);
+        assert_eq!(reparsed.gates["one"].value, Some(true));
+
+        let blif = ".model consts\n.outputs z00 z01\n.names z00\n.names z01\n1\n.end\n";
+        let machine = GateMachine::from_blif(blif).unwrap();
+        assert_eq!(machine.to_blif("consts"), blif);
+        assert_eq!(
+            machine.to_verilog("consts"),
+            "module consts(z00, z01);
+    output z00, z01;
+    assign z00 = 1'b0;
+    assign z01 = 1'b1;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn blif_errors() {
+        let err = |blif: &str| GateMachine::from_blif(blif).unwrap_err();
+        assert_eq!(
+            err(".inputs a b\n.names a b c\n11 0\n00 1"),
+            BlifError {
+                line: 2,
+                message: "cover mixes on-set and off-set rows on line 4".to_string()
+            }
+        );
+        assert_eq!(
+            err(".inputs a b\n.names a b c\n11 1\n.names a b c\n11 1"),
+            BlifError {
+                line: 4,
+                message: "wire c is driven twice".to_string()
+            }
+        );
+        assert_eq!(
+            err(".inputs a b\n\n.names a b c\n11 1\n00 1").to_string(),
+            "line 3: a b is not an AND, OR or XOR"
+        );
+        assert_eq!(err(".inputs a b\n.latch a b").message, "unsupported directive .latch");
+        assert_eq!(err(".inputs a\n.outputs q").message, "output q is not driven");
+        assert_eq!(err(".names a b c d\n111 1").message, "gates must have 2 inputs, not 3");
+        assert_eq!(err(".names a b c\n1x 1").message, "invalid cover pattern 1x on line 2");
+    }
+
+    #[test]
+    fn adder_check() {
+        let mut machine = parse(ADDER);
+        let failure = machine.check_adder(100, 1).unwrap_err();
+        let AdderFailure::WrongSum { bit, cone, .. } = &failure else {
+            panic!("expected a wrong sum, got {:?}", failure);
+        };
+        assert_eq!(*bit, 2);
+        assert_eq!(cone, &["cr0", "cr1", "gn1", "gn2", "hs1", "hs2", "pr1", "pr2", "z02"]);
+        assert!(failure.to_string().contains("first wrong at z02 (driven by cr0,cr1,"));
+
+        machine.swap_outputs("z02", "cr2").unwrap();
+        let AdderFailure::WrongSum { bit, .. } = machine.check_adder(100, 1).unwrap_err() else {
+            panic!("expected a wrong sum");
+        };
+        assert_eq!(bit, 3);
+        machine.swap_outputs("hs3", "gn3").unwrap();
+        assert_eq!(machine.check_adder(100, 1), Ok(4 + 4 * 5 + 100));
+
+        // a swap that feeds a gate's output back into itself
+        machine.swap_outputs("hs1", "z01").unwrap();
+        assert!(matches!(
+            machine.check_adder(0, 0),
+            Err(AdderFailure::Invalid(NetlistError::Cycle(_)))
+        ));
+        assert_eq!(
+            parse(EXAMPLE1).check_adder(0, 0),
+            Err(AdderFailure::BusWidths { x: 3, y: 3, z: 3 })
+        );
     }
 }
@@ -1,4 +1,5 @@
 use aoc_runner_derive::aoc;
+use grid::Grid;
 use itertools::Itertools;
 
 enum LockKey {
@@ -12,55 +13,30 @@ struct LockPile {
     locks: Vec<Vec<usize>>,
 }
 
-fn parse_grid(lines: &Vec<&str>) -> (LockKey, Vec<usize>) {
-    assert_eq!(lines.len(), 7);
-    if lines[0].chars().all(|c| c == '#') {
-        // lock
-        let mut pins = vec![0; 5];
-        for row in 1..lines.len() {
-            let row_s = lines[row];
-            for i in 0..row_s.len() {
-                if row_s.chars().nth(i) == Some('#') {
-                    pins[i] = row
-                }
-            }
-        }
-        (LockKey::Lock, pins)
-    } else if lines[6].chars().all(|c| c == '#') {
-        // key
-        let mut pins = vec![5; 5];
-        for row in (1..lines.len()).rev() {
-            let row_s = lines[row];
-            for i in 0..row_s.len() {
-                if row_s.chars().nth(i) == Some('#') {
-                    pins[i] = 6 - row
-                }
-            }
-        }
-        (LockKey::Key, pins)
+fn parse_grid(grid: &Grid<u8>) -> (LockKey, Vec<usize>) {
+    assert_eq!((grid.width(), grid.height()), (5, 7));
+    let kind = if grid.row(0).unwrap().iter().all(|c| *c == b'#') {
+        LockKey::Lock
+    } else if grid.row(6).unwrap().iter().all(|c| *c == b'#') {
+        LockKey::Key
     } else {
-        panic!("not a lock or a key: {:?}", lines);
-    }
+        panic!("not a lock or a key:\n{}", grid);
+    };
+    // each column of the grid is one pin, its height is the number of '#' not counting the base
+    let columns = grid.transpose();
+    let pins = (0..columns.height() as i64)
+        .map(|x| columns.row(x).unwrap().iter().filter(|c| **c == b'#').count() - 1)
+        .collect();
+    (kind, pins)
 }
 
 fn parse(input: &str) -> LockPile {
     let mut locks = Vec::new();
     let mut keys = Vec::new();
-    let mut accum: Vec<&str> = Vec::new();
-    for line in input.lines() {
-        if line == "" {
-            let (lk, pins) = parse_grid(&accum);
-            match lk {
-                LockKey::Lock => locks.push(pins),
-                LockKey::Key => keys.push(pins),
-            }
-            accum.clear();
-        } else {
-            accum.push(line);
-        }
-    }
-    if accum.len() != 0 {
-        let (lk, pins) = parse_grid(&accum);
+    // blocks are separated by blank lines; `lines` also strips any `\r`, so CRLF input works too
+    let blocks = input.lines().chunk_by(|line| line.is_empty());
+    for (_, mut block) in blocks.into_iter().filter(|(blank, _)| !blank) {
+        let (lk, pins) = parse_grid(&block.join("\n").parse().unwrap());
         match lk {
             LockKey::Lock => locks.push(pins),
             LockKey::Key => keys.push(pins),
@@ -138,6 +114,12 @@ mod tests {
         assert_eq!(part1(EXAMPLE), 3);
     }
 
+    #[test]
+    fn crlf() {
+        assert_eq!(part1(&EXAMPLE.replace('\n', "\r\n")), 3);
+        assert_eq!(part1(&format!("\n{}\n\n\n", EXAMPLE.replace("\n\n", "\n\n\n"))), 3);
+    }
+
     #[test]
     fn part2_example() {}
 }
@@ -6,13 +6,41 @@ use std::fmt;
 use std::io::BufRead;
 use std::ops::BitAnd;
 
-use grid::Grid;
+use grid::{CellCodec, Grid};
 
 #[aoc_generator(day6)]
 pub fn get_input(input: &[u8]) -> Map {
     Map::from(input)
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Cell {
+    Open,
+    Obstacle,
+    Guard,
+    Visited,
+}
+
+impl CellCodec for Cell {
+    fn decode(symbol: u8) -> Option<Self> {
+        match symbol {
+            b'.' => Some(Cell::Open),
+            b'#' => Some(Cell::Obstacle),
+            b'^' => Some(Cell::Guard),
+            b'X' => Some(Cell::Visited),
+            _ => None,
+        }
+    }
+    fn encode(&self) -> char {
+        match self {
+            Cell::Open => '.',
+            Cell::Obstacle => '#',
+            Cell::Guard => '^',
+            Cell::Visited => 'X',
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum FacingDirection {
@@ -89,7 +117,7 @@ impl BitAnd<FacingDirection> for DirectionSet {
 
 #[derive(Clone)]
 pub struct Map {
-    grid: Grid<u8>,
+    grid: Grid<Cell>,
     visited_from: Grid<DirectionSet>,
     guard_facing: FacingDirection,
     guard_pos: (i64, i64),
@@ -98,9 +126,9 @@ pub struct Map {
 
 impl<T: BufRead> From<T> for Map {
     fn from(input: T) -> Self {
-        let grid = Grid::from(input);
+        let grid: Grid<Cell> = Grid::try_from_reader(input).unwrap_or_else(|e| panic!("invalid map: {}", e));
         let visited_from = grid.same_shape(DirectionSet::empty());
-        let guard_pos = grid.find(&b'^').expect("Guard not found");
+        let guard_pos = grid.find(&Cell::Guard).expect("Guard not found");
         let guard_facing = FacingDirection::Up;
         Self {
             grid,
@@ -113,7 +141,7 @@ impl<T: BufRead> From<T> for Map {
 }
 
 impl Map {
-    fn look(&self, dir: &FacingDirection) -> Option<&u8> {
+    fn look(&self, dir: &FacingDirection) -> Option<&Cell> {
         self.grid.get(&dir.pos_ofs(self.guard_pos))
     }
     /// Move one step in the facing direction, return if we are still inside the bounds
@@ -125,7 +153,7 @@ impl Map {
             .is_some_and(|dirs| dirs.contains(self.guard_facing.into()))
         {
             StepOutcome::LoopFound
-        } else if self.grid.set(&new_pos, b'X').is_some() {
+        } else if self.grid.set(&new_pos, Cell::Visited).is_some() {
             if RECORD_PATH {
                 self.path.push((new_pos, self.guard_facing));
             }
@@ -142,7 +170,7 @@ impl Map {
     fn run_guard<const RECORD_PATH: bool>(&mut self) -> RunOutcome {
         while let Some(val) = self.look(&self.guard_facing) {
             match val {
-                b'#' => {
+                Cell::Obstacle => {
                     // obstacle, turn right
                     self.guard_facing = self.guard_facing.next();
                 }
@@ -163,7 +191,7 @@ pub fn part1(map: &Map) -> u64 {
     let mut map = map.clone();
     map.run_guard::<false>();
 
-    map.grid.count(&b'X') as u64
+    map.grid.count(&Cell::Visited) as u64
 }
 
 // PROBLEM 2 solution
@@ -179,7 +207,7 @@ pub fn part2(input_map: &Map) -> u64 {
         .filter(|prev_cur| {
             let last_posdir = prev_cur[0];
             let mut test_map = input_map.clone();
-            test_map.grid.set(&prev_cur[1].0, b'#').unwrap();
+            test_map.grid.set(&prev_cur[1].0, Cell::Obstacle).unwrap();
             test_map.guard_pos = last_posdir.0;
             test_map.guard_facing = last_posdir.1;
 
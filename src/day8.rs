@@ -30,15 +30,7 @@ impl AntennaMap {
 
         // for each unique frequency, get all the pairs' positions
         for freq in freq_set {
-            for pair in self
-                .map
-                .data
-                .iter()
-                .enumerate()
-                .filter(|(_, c)| *c == freq)
-                .map(|(i, _)| self.map.coord(i as i64).unwrap())
-                .permutations(2)
-            {
+            for pair in self.map.find_all(freq).permutations(2) {
                 // permutations generates both pairs, ie. ((1,2),(2,1)) and ((2,1),(1,2)) so we don't need
                 // to consider the 'negative' side of the line, which will be generated by the other pair
                 let (a, b) = (pair[0], pair[1]);
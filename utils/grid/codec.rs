@@ -0,0 +1,271 @@
+//! Text encoding and decoding of [Grid] cells.
+
+use crate::{Coord2d, Grid};
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Write},
+    io::BufRead,
+    str::FromStr,
+};
+
+/// Maps between a grid cell and the single character that represents it in puzzle text
+pub trait CellCodec: Sized {
+    /// Decode an input byte, or `None` if it doesn't represent a cell of this type
+    fn decode(symbol: u8) -> Option<Self>;
+    /// The character this cell is rendered as
+    fn encode(&self) -> char;
+}
+
+impl CellCodec for u8 {
+    fn decode(symbol: u8) -> Option<Self> {
+        Some(symbol)
+    }
+    fn encode(&self) -> char {
+        *self as char
+    }
+}
+
+impl CellCodec for char {
+    fn decode(symbol: u8) -> Option<Self> {
+        Some(symbol as char)
+    }
+    fn encode(&self) -> char {
+        *self
+    }
+}
+
+/// Open (`true`) and blocked (`false`) cells, rendered as `.` and `#`
+impl CellCodec for bool {
+    fn decode(symbol: u8) -> Option<Self> {
+        match symbol {
+            b'.' => Some(true),
+            b'#' => Some(false),
+            _ => None,
+        }
+    }
+    fn encode(&self) -> char {
+        match self {
+            true => '.',
+            false => '#',
+        }
+    }
+}
+
+/// Errors encountered while decoding a [Grid] from text
+#[derive(Debug)]
+pub enum GridParseError {
+    /// Reading the input failed
+    Io(std::io::Error),
+    /// The input contained no rows
+    Empty,
+    /// A row's width differs from the first row's. `line` is 1-based.
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A blank line was followed by more rows. Blank lines are only allowed at the end of the input.
+    BlankLine { line: usize },
+    /// A symbol that the cell type's [CellCodec] doesn't recognise
+    UnknownSymbol { symbol: u8, pos: Coord2d },
+}
+
+impl Display for GridParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridParseError::Io(e) => write!(f, "failed to read grid: {}", e),
+            GridParseError::Empty => f.write_str("grid is empty"),
+            GridParseError::RaggedRow { line, expected, actual } => {
+                write!(f, "line {}: expected row of width {}, got {}", line, expected, actual)
+            }
+            GridParseError::BlankLine { line } => write!(f, "line {}: blank line inside grid", line),
+            GridParseError::UnknownSymbol { symbol, pos } => {
+                write!(f, "unknown symbol {:?} at ({}, {})", *symbol as char, pos.x, pos.y)
+            }
+        }
+    }
+}
+
+impl Error for GridParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GridParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GridParseError {
+    fn from(value: std::io::Error) -> Self {
+        GridParseError::Io(value)
+    }
+}
+
+impl<T: CellCodec + Clone + Eq + PartialEq + Debug> Grid<T> {
+    /// Read a grid, one row per line, mapping each byte through the cell type's [CellCodec]. Line endings may be `\n`
+    /// or `\r\n`, and blank lines at the end of the input are ignored.
+    pub fn try_from_reader<R: BufRead>(input: R) -> Result<Self, GridParseError> {
+        let mut data = Vec::new();
+        let mut width = 0;
+        let mut height = 0;
+        let mut blank_line = None;
+        for (y, line) in input.split(b'\n').enumerate() {
+            let mut line = line?;
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.is_empty() {
+                blank_line.get_or_insert(y + 1);
+                continue;
+            }
+            if let Some(line) = blank_line {
+                return Err(GridParseError::BlankLine { line });
+            }
+            if height == 0 {
+                width = line.len();
+            } else if line.len() != width {
+                return Err(GridParseError::RaggedRow {
+                    line: y + 1,
+                    expected: width,
+                    actual: line.len(),
+                });
+            }
+            for (x, symbol) in line.iter().enumerate() {
+                match T::decode(*symbol) {
+                    Some(cell) => data.push(cell),
+                    None => {
+                        return Err(GridParseError::UnknownSymbol {
+                            symbol: *symbol,
+                            pos: Coord2d {
+                                x: x as i64,
+                                y: y as i64,
+                            },
+                        })
+                    }
+                }
+            }
+            height += 1;
+        }
+        if height == 0 {
+            return Err(GridParseError::Empty);
+        }
+        Ok(Grid {
+            data,
+            width: width as i64,
+        })
+    }
+    /// Decode a grid from in-memory text, as for [Grid::try_from_reader]
+    pub fn decode(input: &[u8]) -> Result<Self, GridParseError> {
+        Self::try_from_reader(input)
+    }
+    /// [Grid::try_from_reader], except that input with no rows gives an empty grid, as `parse` and `From` always have
+    pub(crate) fn try_from_reader_or_empty<R: BufRead>(input: R) -> Result<Self, GridParseError> {
+        match Self::try_from_reader(input) {
+            Err(GridParseError::Empty) => Ok(Grid {
+                data: Vec::new(),
+                width: 0,
+            }),
+            result => result,
+        }
+    }
+}
+
+impl<T: CellCodec + Clone + Eq + PartialEq + Debug> FromStr for Grid<T> {
+    type Err = GridParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_reader_or_empty(s.as_bytes())
+    }
+}
+
+impl<T: CellCodec + Clone + Eq + PartialEq + Debug> Display for Grid<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                f.write_char(self.get(&(x as i64, y as i64)).unwrap().encode())?;
+            }
+            f.write_char('\n')?;
+        }
+        f.write_char('\n')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum Tile {
+        Wall,
+        Floor,
+    }
+
+    impl CellCodec for Tile {
+        fn decode(symbol: u8) -> Option<Self> {
+            match symbol {
+                b'#' => Some(Tile::Wall),
+                b'.' => Some(Tile::Floor),
+                _ => None,
+            }
+        }
+        fn encode(&self) -> char {
+            match self {
+                Tile::Wall => '#',
+                Tile::Floor => '.',
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let grid = Grid::<Tile>::decode(b"##.\n.#.\n").unwrap();
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!(grid.get(&(2, 0)), Some(&Tile::Floor));
+        assert_eq!(grid.to_string(), "##.\n.#.\n\n");
+        assert_eq!(Grid::<Tile>::decode(grid.to_string().as_bytes()).unwrap(), grid);
+        assert_eq!(Grid::<bool>::decode(b"#.\n..").unwrap().to_string(), "#.\n..\n\n");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Grid::<Tile>::decode(b"##.\n.#\n"),
+            Err(GridParseError::RaggedRow {
+                line: 2,
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            Grid::<Tile>::decode(b"##.\n.#O\n"),
+            Err(GridParseError::UnknownSymbol {
+                symbol: b'O',
+                pos: Coord2d { x: 2, y: 1 }
+            })
+        ));
+        assert!(matches!(Grid::<Tile>::decode(b""), Err(GridParseError::Empty)));
+        assert!(matches!(Grid::<Tile>::decode(b"\n\n"), Err(GridParseError::Empty)));
+        assert!(matches!(
+            Grid::<Tile>::decode(b"##\n\n.#\n"),
+            Err(GridParseError::BlankLine { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn line_endings() {
+        let grid = Grid::<Tile>::decode(b"##.\r\n.#.\r\n\r\n\n").unwrap();
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 2);
+        assert_eq!("#.\n.#".parse::<Grid<Tile>>().unwrap().get(&(1, 1)), Some(&Tile::Wall));
+    }
+
+    #[test]
+    fn empty_input() {
+        // only the decoding API rejects empty input, `parse` and `From` give an empty grid
+        assert!(matches!(Grid::<u8>::decode(b""), Err(GridParseError::Empty)));
+        for grid in ["".parse::<Grid<u8>>().unwrap(), Grid::from(&b"\n"[..])] {
+            assert_eq!(grid.width(), 0);
+            assert!(grid.data.is_empty());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! A disjoint-set (union-find) structure over dense indices.
+
+/// A partition of `0..len` into disjoint sets, merged with [DisjointSet::union]. Uses union by size and path
+/// halving, so any sequence of operations runs in near-linear time.
+#[derive(Clone, Debug)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    sets: usize,
+}
+
+impl DisjointSet {
+    /// `len` elements, each in a set of its own
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+            sets: len,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+    /// The number of distinct sets
+    pub fn sets(&self) -> usize {
+        self.sets
+    }
+    /// The representative element of the set containing `x`
+    pub fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+    /// Merge the sets containing `a` and `b`, returning false if they were already the same set
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.sets -= 1;
+        true
+    }
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+    /// The number of elements in the set containing `x`
+    pub fn set_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut sets = DisjointSet::new(6);
+        assert_eq!((sets.len(), sets.sets()), (6, 6));
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+        assert_eq!(sets.sets(), 3);
+        assert!(sets.connected(0, 3));
+        assert!(!sets.connected(0, 4));
+        assert_eq!(sets.set_size(2), 4);
+        assert_eq!(sets.set_size(5), 1);
+        assert!(DisjointSet::new(0).is_empty());
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut sets = DisjointSet::new(10_000);
+        for i in 1..10_000 {
+            sets.union(i - 1, i);
+        }
+        assert_eq!(sets.sets(), 1);
+        assert_eq!(sets.set_size(0), 10_000);
+        assert_eq!(sets.find(9_999), sets.find(0));
+    }
+}
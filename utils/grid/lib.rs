@@ -1,12 +1,26 @@
 use std::{
-    fmt::{Debug, Display, Formatter, Write},
-    io::{BufRead, Cursor},
+    fmt::Debug,
+    io::BufRead,
     iter::repeat_n,
     mem::swap,
     ops::{Add, AddAssign, Sub},
-    str::FromStr,
 };
 
+mod codec;
+mod disjoint;
+#[cfg(feature = "rayon")]
+mod par;
+mod pattern;
+mod region;
+pub mod search;
+mod transform;
+
+pub use codec::{CellCodec, GridParseError};
+pub use disjoint::DisjointSet;
+pub use pattern::{LineMatch, Template};
+pub use region::{Connectivity, Region, Regions};
+pub use transform::GridView;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Coord2d {
     pub x: i64,
@@ -117,6 +131,30 @@ impl From<Coord2d> for (i64, i64) {
     }
 }
 
+/// Offsets of the orthogonally adjacent cells: up, right, down, left
+pub const NEIGHBORS4: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+/// Offsets of the orthogonally and diagonally adjacent cells, clockwise from up
+pub const NEIGHBORS8: [(i64, i64); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+/// An axis-aligned rectangle of cells, `min` and `max` inclusive
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Rect {
+    pub min: Coord2d,
+    pub max: Coord2d,
+}
+
+impl Rect {
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+    pub fn contains<C: AsCoord2d>(&self, c: &C) -> bool {
+        (self.min.x..=self.max.x).contains(&c.x()) && (self.min.y..=self.max.y).contains(&c.y())
+    }
+}
+
 #[derive(Debug)]
 pub struct GridRowIter<'a, T> {
     iter: std::slice::Iter<'a, T>,
@@ -209,9 +247,14 @@ impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
             })
         }
     }
-    // pub fn coord_iter(&self) -> CoordIter<_> {
-    //     CoordIter { pos: 0, grid: self }
-    // }
+    /// Every coordinate in the grid, in row-major order
+    pub fn coords(&self) -> impl Iterator<Item = Coord2d> {
+        let width = self.width;
+        (0..self.data.len() as i64).map(move |pos| Coord2d {
+            x: pos % width,
+            y: pos / width,
+        })
+    }
     pub fn is_valid<C: AsCoord2d>(&self, c: &C) -> bool {
         if c.x() < 0 || c.x() >= self.width {
             return false;
@@ -311,6 +354,22 @@ impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
     pub fn count(&self, haystack: &T) -> usize {
         self.data.iter().filter(|item| *item == haystack).count()
     }
+    /// Every cell with its coordinate, in row-major order
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (Coord2d, &T)> {
+        self.coords().zip(self.data.iter())
+    }
+    /// Every cell with its coordinate, in row-major order, mutably
+    pub fn iter_enumerated_mut(&mut self) -> impl Iterator<Item = (Coord2d, &mut T)> {
+        self.coords().zip(self.data.iter_mut())
+    }
+    /// The coordinates of every cell equal to `needle`, in row-major order
+    pub fn find_all<'a>(&'a self, needle: &'a T) -> impl Iterator<Item = Coord2d> + 'a {
+        self.positions(move |val| val == needle)
+    }
+    /// The coordinates of every cell satisfying `pred`, in row-major order
+    pub fn positions<'a, F: FnMut(&T) -> bool + 'a>(&'a self, mut pred: F) -> impl Iterator<Item = Coord2d> + 'a {
+        self.iter_enumerated().filter(move |(_, val)| pred(val)).map(|(c, _)| c)
+    }
 
     pub fn forward_slice<C: AsCoord2d>(&self, start: &C, len: i64) -> Option<&[T]> {
         let pos = (self.valid_pos(start), self.valid_pos(&(start.x() + len - 1, start.y())));
@@ -326,92 +385,56 @@ impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
         }
     }
 
-    // fn window_compare_impl<const REV: bool>(&self, needle: &[T]) -> Vec<(i64, i64)> {
-    //     if (self.width as usize) < needle.len() {
-    //         return Vec::new();
-    //     }
-    //     let mut res = Vec::new();
-    //     for y in 0..self.height() as i64 {
-    //         let mut windows_tmp = self.row(y).unwrap().windows(needle.len());
-    //         let windows = if REV {
-    //             windows_tmp.rev()
-    //         } else {
-    //             windows_tmp
-    //         };
-
-    //         res.extend(
-    //             windows
-    //                 .enumerate()
-    //                 .filter_map(|(x, w)| if w == needle { Some((x as i64, y)) } else { None }),
-    //         );
-    //     }
-    //     res
-    // }
+    /// The cells at each of the `stencil` offsets from `pos`, skipping any that fall outside the grid
+    pub fn neighbors_with<'a, C: AsCoord2d>(
+        &'a self,
+        pos: &C,
+        stencil: &'a [(i64, i64)],
+    ) -> impl Iterator<Item = (Coord2d, &'a T)> + 'a {
+        let (x, y) = (pos.x(), pos.y());
+        stencil.iter().filter_map(move |(x_ofs, y_ofs)| {
+            let neighbor = Coord2d {
+                x: x + x_ofs,
+                y: y + y_ofs,
+            };
+            self.get(&neighbor).map(|val| (neighbor, val))
+        })
+    }
+    /// The orthogonally adjacent cells of `pos` that are inside the grid
+    pub fn neighbors4<'a, C: AsCoord2d>(&'a self, pos: &C) -> impl Iterator<Item = (Coord2d, &'a T)> + 'a {
+        self.neighbors_with(pos, &NEIGHBORS4)
+    }
+    /// The orthogonally and diagonally adjacent cells of `pos` that are inside the grid
+    pub fn neighbors8<'a, C: AsCoord2d>(&'a self, pos: &C) -> impl Iterator<Item = (Coord2d, &'a T)> + 'a {
+        self.neighbors_with(pos, &NEIGHBORS8)
+    }
+    /// The cells at each of the `stencil` offsets from `pos`, treating the grid as a torus so that offsets past an
+    /// edge wrap around to the opposite side. An empty grid has no cells to wrap onto, so yields none.
+    pub fn neighbors_wrapping<'a, C: AsCoord2d>(
+        &'a self,
+        pos: &C,
+        stencil: &'a [(i64, i64)],
+    ) -> impl Iterator<Item = (Coord2d, &'a T)> + 'a {
+        let (x, y) = (pos.x(), pos.y());
+        let (stencil, height) = if self.data.is_empty() {
+            (&[][..], 0)
+        } else {
+            (stencil, self.height() as i64)
+        };
+        stencil.iter().map(move |(x_ofs, y_ofs)| {
+            let neighbor = Coord2d {
+                x: (x + x_ofs).rem_euclid(self.width),
+                y: (y + y_ofs).rem_euclid(height),
+            };
+            (neighbor, self.get(&neighbor).unwrap())
+        })
+    }
 }
 
 impl<T: BufRead> From<T> for Grid<u8> {
+    /// Read a grid of raw bytes, panicking on malformed input. Use [Grid::try_from_reader] to handle errors.
     fn from(input: T) -> Grid<u8> {
-        let mut data = Vec::new();
-        let mut width = 0;
-        for line in input.split(b'\n').map(|i| i.unwrap()) {
-            if width == 0 {
-                width = line.len() as i64
-            } else if line.len() as i64 != width {
-                panic!("Grids must have fixed length rows")
-            }
-            data.extend_from_slice(&line);
-        }
-        Grid { data, width }
-    }
-}
-
-// Should be Grid<char>?
-impl FromStr for Grid<u8> {
-    type Err = Box<dyn std::error::Error>;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Cursor::new(s).into())
-    }
-}
-
-// impl<T: Copy + Eq + PartialEq + Display + Debug + Into<char>> Display for Grid<T> {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         for y in 0..self.height() {
-//             for x in 0..self.width() {
-//                 f.write_fmt(format_args!("{}",self.get(x as i64, y as i64).unwrap() as char))?;
-//             }
-//             f.write_char('\n')?;
-//         }
-//         f.write_char('\n')
-//     }
-// }
-
-impl Display for Grid<u8> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.height() {
-            for x in 0..self.width() {
-                f.write_fmt(format_args!("{}", *self.get(&(x as i64, y as i64)).unwrap() as char))?;
-            }
-            f.write_char('\n')?;
-        }
-        f.write_char('\n')
-    }
-}
-
-impl Display for Grid<bool> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.height() {
-            for x in 0..self.width() {
-                f.write_fmt(format_args!(
-                    "{}",
-                    match *self.get(&(x as i64, y as i64)).unwrap() {
-                        true => '.',
-                        false => '#',
-                    }
-                ))?;
-            }
-            f.write_char('\n')?;
-        }
-        f.write_char('\n')
+        Grid::try_from_reader_or_empty(input).unwrap_or_else(|e| panic!("invalid grid: {}", e))
     }
 }
 
@@ -485,11 +508,54 @@ FBCG";
         assert!(grid.col_iter(4).is_none());
     }
 
-    // #[test]
-    // fn window_compare() {
-    //     let grid = unchecked_load();
-    //     assert_eq!(grid.window_compare(b"IJKL"), &[(0, 2)]);
-    //     assert_eq!(grid.window_compare(b"BC"), &[(1, 0), (1, 3)]);
-    //     assert_eq!(grid.window_compare(b"LF").len(), 0);
-    // }
+    #[test]
+    fn enumerated() {
+        let mut grid = unchecked_load();
+        assert_eq!(grid.coords().nth(6), Some(Coord2d { x: 2, y: 1 }));
+        assert_eq!(grid.coords().count(), 16);
+        assert_eq!(grid.iter_enumerated().nth(13), Some((Coord2d { x: 1, y: 3 }, &b'B')));
+        assert_eq!(
+            grid.find_all(&b'B').collect::<Vec<_>>(),
+            [Coord2d { x: 1, y: 0 }, Coord2d { x: 1, y: 3 }]
+        );
+        assert_eq!(grid.positions(|c| *c > b'J').count(), 2);
+        for (c, val) in grid.iter_enumerated_mut() {
+            if c.x == c.y {
+                *val = b'.';
+            }
+        }
+        assert_eq!(grid.find_all(&b'.').count(), 4);
+    }
+
+    #[test]
+    fn neighbors() {
+        let grid = unchecked_load();
+        assert_eq!(
+            grid.neighbors4(&(1, 1)).collect::<Vec<_>>(),
+            [
+                (Coord2d { x: 1, y: 0 }, &b'B'),
+                (Coord2d { x: 2, y: 1 }, &b'G'),
+                (Coord2d { x: 1, y: 2 }, &b'J'),
+                (Coord2d { x: 0, y: 1 }, &b'E'),
+            ]
+        );
+        assert_eq!(grid.neighbors4(&(0, 0)).map(|(_, v)| *v).collect::<Vec<_>>(), b"BE");
+        assert_eq!(grid.neighbors8(&(0, 0)).map(|(_, v)| *v).collect::<Vec<_>>(), b"BFE");
+        assert_eq!(grid.neighbors8(&(2, 2)).count(), 8);
+        assert_eq!(
+            grid.neighbors_with(&(3, 3), &[(0, 0), (-2, -2), (1, 1)])
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>(),
+            b"GF"
+        );
+        assert_eq!(
+            grid.neighbors_wrapping(&(0, 0), &NEIGHBORS4)
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>(),
+            b"FBED"
+        );
+        for empty in [Grid::with_shape(0, 3, b'.'), Grid::with_shape(4, 0, b'.')] {
+            assert_eq!(empty.neighbors_wrapping(&(0, 0), &NEIGHBORS8).count(), 0);
+        }
+    }
 }
@@ -0,0 +1,70 @@
+//! Parallel counterparts of the [Grid] scanning iterators, backed by rayon.
+
+use crate::{Coord2d, Grid};
+use rayon::prelude::*;
+use std::fmt::Debug;
+
+impl<T: Clone + Eq + PartialEq + Debug + Sync> Grid<T> {
+    /// Every coordinate in the grid, in parallel
+    pub fn par_coords(&self) -> impl IndexedParallelIterator<Item = Coord2d> {
+        let width = self.width;
+        (0..self.data.len()).into_par_iter().map(move |pos| Coord2d {
+            x: pos as i64 % width,
+            y: pos as i64 / width,
+        })
+    }
+    /// Every cell with its coordinate, in parallel
+    pub fn par_iter_enumerated(&self) -> impl IndexedParallelIterator<Item = (Coord2d, &T)> {
+        self.par_coords().zip(self.data.par_iter())
+    }
+    /// The coordinates of every cell equal to `needle`, in parallel
+    pub fn par_find_all<'a>(&'a self, needle: &'a T) -> impl ParallelIterator<Item = Coord2d> + 'a {
+        self.par_positions(move |val| val == needle)
+    }
+    /// The coordinates of every cell satisfying `pred`, in parallel
+    pub fn par_positions<'a, F: Fn(&T) -> bool + Sync + Send + 'a>(
+        &'a self,
+        pred: F,
+    ) -> impl ParallelIterator<Item = Coord2d> + 'a {
+        self.par_iter_enumerated()
+            .filter(move |(_, val)| pred(val))
+            .map(|(c, _)| c)
+    }
+}
+
+impl<T: Clone + Eq + PartialEq + Debug + Send> Grid<T> {
+    /// Every cell with its coordinate, mutably and in parallel
+    pub fn par_iter_enumerated_mut(&mut self) -> impl IndexedParallelIterator<Item = (Coord2d, &mut T)> {
+        let width = self.width;
+        let coords = (0..self.data.len()).into_par_iter().map(move |pos| Coord2d {
+            x: pos as i64 % width,
+            y: pos as i64 / width,
+        });
+        coords.zip(self.data.par_iter_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sequential() {
+        let mut grid: Grid<u8> = "ABCD\nEFGH\nIJKL\nFBCG".parse().unwrap();
+        assert_eq!(grid.par_coords().collect::<Vec<_>>(), grid.coords().collect::<Vec<_>>());
+        assert_eq!(
+            grid.par_iter_enumerated().collect::<Vec<_>>(),
+            grid.iter_enumerated().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            grid.par_find_all(&b'C').collect::<Vec<_>>(),
+            grid.find_all(&b'C').collect::<Vec<_>>()
+        );
+        assert_eq!(
+            grid.par_positions(|c| *c > b'F').count(),
+            grid.positions(|c| *c > b'F').count()
+        );
+        grid.par_iter_enumerated_mut().for_each(|(c, val)| *val = c.x as u8);
+        assert_eq!(grid.row(3), Some([0, 1, 2, 3].as_slice()));
+    }
+}
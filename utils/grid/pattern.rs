@@ -0,0 +1,133 @@
+//! Searching a [Grid] for straight-line sequences and 2-D templates.
+
+use crate::{Coord2d, Grid, NEIGHBORS8};
+use std::fmt::Debug;
+
+/// A sequence found along a straight line of the grid
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LineMatch {
+    /// Coordinate of the first element of the sequence
+    pub start: Coord2d,
+    /// Offset from each element to the next
+    pub direction: (i64, i64),
+}
+
+/// A rectangular pattern to search for, where `None` cells match anything
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Template<T> {
+    cells: Grid<Option<T>>,
+}
+
+impl<T: Clone + Eq + PartialEq + Debug> Template<T> {
+    /// A template with the shape and cells of `pattern`, treating cells equal to `wildcard` as matching anything
+    pub fn new(pattern: &Grid<T>, wildcard: &T) -> Self {
+        Self {
+            cells: Grid {
+                data: pattern
+                    .data
+                    .iter()
+                    .map(|c| if c == wildcard { None } else { Some(c.clone()) })
+                    .collect(),
+                width: pattern.width,
+            },
+        }
+    }
+}
+
+impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
+    /// Find `needle` running in a straight line in any of the eight directions
+    pub fn find_sequence(&self, needle: &[T]) -> Vec<LineMatch> {
+        self.find_sequence_in(needle, &NEIGHBORS8)
+    }
+    /// Find `needle` running in a straight line in any of the given `directions`
+    pub fn find_sequence_in(&self, needle: &[T], directions: &[(i64, i64)]) -> Vec<LineMatch> {
+        let mut matches = Vec::new();
+        let Some(first) = needle.first() else {
+            return matches;
+        };
+        for start in self.find_all(first) {
+            for direction in directions {
+                if needle.iter().enumerate().skip(1).all(|(i, val)| {
+                    self.get(&(start.x + direction.0 * i as i64, start.y + direction.1 * i as i64)) == Some(val)
+                }) {
+                    matches.push(LineMatch {
+                        start,
+                        direction: *direction,
+                    });
+                }
+            }
+        }
+        matches
+    }
+    /// Find every placement of `template` that fits inside the grid with all its non-wildcard cells matching,
+    /// returning the coordinate of the template's top left corner
+    pub fn find_template(&self, template: &Template<T>) -> Vec<Coord2d> {
+        let (t_width, t_height) = (template.cells.width(), template.cells.height());
+        if t_width > self.width() || t_height > self.height() {
+            return Vec::new();
+        }
+        let mut matches = Vec::new();
+        for y in 0..=(self.height() - t_height) as i64 {
+            for x in 0..=(self.width() - t_width) as i64 {
+                if template.cells.iter_enumerated().all(|(ofs, cell)| {
+                    cell.as_ref()
+                        .is_none_or(|c| self.get(&(x + ofs.x, y + ofs.y)) == Some(c))
+                }) {
+                    matches.push(Coord2d { x, y });
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "ABCD
+EFGH
+IJKL
+FBCG";
+
+    #[test]
+    fn sequences() {
+        let grid: Grid<u8> = GRID.parse().unwrap();
+        assert_eq!(
+            grid.find_sequence(b"BC"),
+            [
+                LineMatch {
+                    start: Coord2d { x: 1, y: 0 },
+                    direction: (1, 0)
+                },
+                LineMatch {
+                    start: Coord2d { x: 1, y: 3 },
+                    direction: (1, 0)
+                }
+            ]
+        );
+        assert_eq!(
+            grid.find_sequence(b"GB"),
+            [LineMatch {
+                start: Coord2d { x: 2, y: 1 },
+                direction: (-1, -1)
+            }]
+        );
+        assert_eq!(grid.find_sequence(b"FJ").len(), 2);
+        assert_eq!(grid.find_sequence_in(b"FJ", &crate::NEIGHBORS4).len(), 1);
+        assert_eq!(grid.find_sequence_in(b"FJ", &[(1, 0)]).len(), 0);
+        assert_eq!(grid.find_sequence(b"DHLGX").len(), 0);
+        assert_eq!(grid.find_sequence(b"").len(), 0);
+    }
+
+    #[test]
+    fn templates() {
+        let grid: Grid<u8> = GRID.parse().unwrap();
+        let template = Template::new(&"F.\n.K".parse().unwrap(), &b'.');
+        assert_eq!(grid.find_template(&template), [Coord2d { x: 1, y: 1 }]);
+        let template = Template::new(&"..\n..".parse().unwrap(), &b'.');
+        assert_eq!(grid.find_template(&template).len(), 9);
+        let template = Template::new(&"A....".parse().unwrap(), &b'.');
+        assert_eq!(grid.find_template(&template).len(), 0);
+    }
+}
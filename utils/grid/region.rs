@@ -0,0 +1,199 @@
+//! Connected-component labelling of [Grid] cells and per-region statistics.
+
+use crate::{AsCoord2d, Coord2d, Grid, Rect, NEIGHBORS4, NEIGHBORS8};
+use std::fmt::Debug;
+
+/// Which adjacent cells are considered connected
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Connectivity {
+    /// Orthogonal neighbours only
+    Four,
+    /// Orthogonal and diagonal neighbours
+    Eight,
+}
+
+impl Connectivity {
+    fn stencil(&self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &NEIGHBORS4,
+            Connectivity::Eight => &NEIGHBORS8,
+        }
+    }
+}
+
+/// One connected region of a [Regions] labelling
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Region {
+    /// This region's value in [Regions::labels]
+    pub label: usize,
+    /// Every cell in the region, in the order they were reached
+    pub cells: Vec<Coord2d>,
+    /// Number of cell edges shared with another region or the edge of the grid
+    pub perimeter: usize,
+    /// Number of corners of the region's outline (inside and outside), which is also its number of straight sides
+    pub corners: usize,
+    /// Smallest rectangle containing the region
+    pub bounds: Rect,
+}
+
+impl Region {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+    pub fn sides(&self) -> usize {
+        self.corners
+    }
+}
+
+/// A grid labelled into connected regions
+#[derive(Clone, Debug)]
+pub struct Regions {
+    /// The label of the region each cell belongs to, an index into `regions`
+    pub labels: Grid<usize>,
+    pub regions: Vec<Region>,
+}
+
+impl Regions {
+    /// The region containing `c`, if it's inside the grid
+    pub fn region_of<C: AsCoord2d>(&self, c: &C) -> Option<&Region> {
+        self.labels.get(c).map(|label| &self.regions[*label])
+    }
+
+    fn same<C: AsCoord2d>(&self, label: usize, c: &C) -> bool {
+        self.labels.get(c) == Some(&label)
+    }
+
+    fn perimeter_of(&self, label: usize, c: &Coord2d) -> usize {
+        NEIGHBORS4
+            .iter()
+            .filter(|(x_ofs, y_ofs)| !self.same(label, &(c.x + x_ofs, c.y + y_ofs)))
+            .count()
+    }
+
+    fn corners_of(&self, label: usize, c: &Coord2d) -> usize {
+        [(1i64, 1i64), (-1, 1), (1, -1), (-1, -1)]
+            .iter()
+            .filter(|(x_ofs, y_ofs)| {
+                let horizontal = self.same(label, &(c.x + x_ofs, c.y));
+                let vertical = self.same(label, &(c.x, c.y + y_ofs));
+                let diagonal = self.same(label, &(c.x + x_ofs, c.y + y_ofs));
+                // AB     AA
+                // B. or  A. (with the cell at the top left)
+                (!horizontal && !vertical) || (horizontal && vertical && !diagonal)
+            })
+            .count()
+    }
+}
+
+impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
+    /// Label connected regions of equal cells
+    pub fn regions(&self, connectivity: Connectivity) -> Regions {
+        self.regions_by(connectivity, |a, b| a == b)
+    }
+
+    /// Label connected regions, where adjacent cells `a` and `b` are connected if `same(a, b)`. `same` should be
+    /// symmetric.
+    pub fn regions_by<F: FnMut(&T, &T) -> bool>(&self, connectivity: Connectivity, mut same: F) -> Regions {
+        let mut labels = self.same_shape(usize::MAX);
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+
+        for pos in 0..self.data.len() {
+            if labels.data[pos] != usize::MAX {
+                continue;
+            }
+            let label = regions.len();
+            let start = self.coord(pos as i64).unwrap();
+            let mut cells = Vec::new();
+            let mut bounds = Rect { min: start, max: start };
+
+            labels.set(&start, label);
+            stack.push(start);
+            while let Some(cell) = stack.pop() {
+                cells.push(cell);
+                bounds.min = Coord2d {
+                    x: bounds.min.x.min(cell.x),
+                    y: bounds.min.y.min(cell.y),
+                };
+                bounds.max = Coord2d {
+                    x: bounds.max.x.max(cell.x),
+                    y: bounds.max.y.max(cell.y),
+                };
+                let value = self.get(&cell).unwrap();
+                for (neighbor, neighbor_value) in self.neighbors_with(&cell, connectivity.stencil()) {
+                    if labels.get(&neighbor) == Some(&usize::MAX) && same(value, neighbor_value) {
+                        labels.set(&neighbor, label);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            regions.push(Region {
+                label,
+                cells,
+                perimeter: 0,
+                corners: 0,
+                bounds,
+            });
+        }
+
+        let mut result = Regions { labels, regions };
+        for label in 0..result.regions.len() {
+            let (perimeter, corners) = result.regions[label]
+                .cells
+                .iter()
+                .fold((0, 0), |(perimeter, corners), cell| {
+                    (
+                        perimeter + result.perimeter_of(label, cell),
+                        corners + result.corners_of(label, cell),
+                    )
+                });
+            result.regions[label].perimeter = perimeter;
+            result.regions[label].corners = corners;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "AAAA
+BBCD
+BBCC
+EEEC";
+
+    #[test]
+    fn region_stats() {
+        let grid: Grid<u8> = MAP.parse().unwrap();
+        let regions = grid.regions(Connectivity::Four);
+        assert_eq!(regions.regions.len(), 5);
+        let stats = regions
+            .regions
+            .iter()
+            .map(|r| (r.area(), r.perimeter, r.sides()))
+            .collect::<Vec<_>>();
+        assert_eq!(stats, [(4, 10, 4), (4, 8, 4), (4, 10, 8), (1, 4, 4), (3, 8, 4)]);
+
+        let c = regions.region_of(&(2, 1)).unwrap();
+        assert_eq!(
+            c.bounds,
+            Rect {
+                min: Coord2d { x: 2, y: 1 },
+                max: Coord2d { x: 3, y: 3 }
+            }
+        );
+        assert_eq!(regions.labels.get(&(3, 3)), Some(&c.label));
+        assert!(c.bounds.contains(&(3, 2)));
+        assert!(!c.bounds.contains(&(1, 1)));
+    }
+
+    #[test]
+    fn connectivity() {
+        let grid: Grid<u8> = "X.X\n.X.\nX.X".parse().unwrap();
+        assert_eq!(grid.regions(Connectivity::Four).regions.len(), 9);
+        assert_eq!(grid.regions(Connectivity::Eight).regions.len(), 2);
+        let by_class = grid.regions_by(Connectivity::Four, |a, b| (*a == b'X') == (*b == b'X'));
+        assert_eq!(by_class.regions.len(), 9);
+    }
+}
@@ -0,0 +1,634 @@
+//! Generic graph searches (BFS, Dijkstra, A*, Yen's k-shortest paths) over arbitrary states.
+//!
+//! Searches are driven by a neighbour closure that returns the states reachable from a given state (with step costs
+//! for the weighted searches) and a goal predicate. What gets recorded about the found path is chosen by the caller
+//! through [PathTrack], and where it records per-state data through [StateMap].
+
+use crate::{AsCoord2d, Grid};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    fmt::Debug,
+    hash::Hash,
+    ops::Add,
+};
+
+/// Chooses what a search records about the path it finds
+pub trait PathTrack<N> {
+    /// If false, the search does not keep predecessors and the tracker is returned without any nodes pushed
+    const DOES_WORK: bool = true;
+    fn new() -> Self;
+    /// Called for each node on the path, from the goal back to the start
+    fn push(&mut self, node: N);
+    /// Called once all nodes have been pushed
+    fn finalize(&mut self) {}
+}
+
+/// Records the number of nodes on the path, including both the start and the goal
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LengthPath(pub usize);
+impl<N> PathTrack<N> for LengthPath {
+    fn new() -> Self {
+        LengthPath(0)
+    }
+    fn push(&mut self, _: N) {
+        self.0 += 1
+    }
+}
+
+/// Records the full path, ordered from start to goal
+impl<N> PathTrack<N> for Vec<N> {
+    fn new() -> Self {
+        Vec::new()
+    }
+    fn push(&mut self, node: N) {
+        Vec::push(self, node);
+    }
+    fn finalize(&mut self) {
+        self.reverse();
+    }
+}
+
+/// Records nothing, only reachability is of interest
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoopTrack;
+impl<N> PathTrack<N> for NoopTrack {
+    const DOES_WORK: bool = false;
+    fn new() -> Self {
+        NoopTrack
+    }
+    fn push(&mut self, _: N) {}
+}
+
+/// Where a search records data about the states it has reached. A [HashMap] works for any state, while a [Grid] of
+/// `Option`s avoids hashing when the states are coordinates covering most of the grid.
+pub trait StateMap<N, V> {
+    fn get(&self, node: &N) -> Option<&V>;
+    fn insert(&mut self, node: N, value: V);
+}
+
+impl<N: Eq + Hash, V> StateMap<N, V> for HashMap<N, V> {
+    fn get(&self, node: &N) -> Option<&V> {
+        HashMap::get(self, node)
+    }
+    fn insert(&mut self, node: N, value: V) {
+        HashMap::insert(self, node, value);
+    }
+}
+
+/// States must lie inside the grid, inserting one outside it panics
+impl<N: AsCoord2d, V: Clone + Eq + Debug> StateMap<N, V> for Grid<Option<V>> {
+    fn get(&self, node: &N) -> Option<&V> {
+        Grid::get(self, node).and_then(|v| v.as_ref())
+    }
+    fn insert(&mut self, node: N, value: V) {
+        let (x, y) = (node.x(), node.y());
+        *self
+            .get_mut(&node)
+            .unwrap_or_else(|| panic!("state ({}, {}) is outside the grid", x, y)) = Some(value);
+    }
+}
+
+/// Path costs usable by the weighted searches. [Default] must be the zero cost.
+pub trait Cost: Copy + Ord + Add<Output = Self> + Default {}
+impl<C: Copy + Ord + Add<Output = C> + Default> Cost for C {}
+
+/// Priority queue entry, ordered so that [BinaryHeap] pops the lowest priority first
+struct Frontier<N, C> {
+    priority: C,
+    cost: C,
+    node: N,
+}
+
+impl<N, C: Ord> Ord for Frontier<N, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.cmp(&self.priority)
+    }
+}
+
+impl<N, C: Ord> PartialOrd for Frontier<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord> PartialEq for Frontier<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl<N, C: Ord> Eq for Frontier<N, C> {}
+
+/// Follow `prev` back from `goal` to a state without a predecessor
+fn reconstruct<N, T: PathTrack<N>>(goal: N, mut prev: impl FnMut(&N) -> Option<N>) -> T {
+    let mut path = T::new();
+    if T::DOES_WORK {
+        let mut node = goal;
+        loop {
+            let next = prev(&node);
+            path.push(node);
+            match next {
+                Some(next) => node = next,
+                None => break,
+            }
+        }
+        path.finalize();
+    }
+    path
+}
+
+/// Breadth-first search from `start` to the first state satisfying `goal`. Every step costs the same.
+pub fn bfs<N, T, FN, IN, FG>(start: N, neighbours: FN, goal: FG) -> Option<T>
+where
+    N: Clone + Eq + Hash,
+    T: PathTrack<N>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+    FG: FnMut(&N) -> bool,
+{
+    bfs_in(start, neighbours, goal, HashMap::new())
+}
+
+/// [bfs] recording each reached state's predecessor (`None` for the start) in `visited`, which should start empty
+pub fn bfs_in<N, T, S, FN, IN, FG>(start: N, mut neighbours: FN, mut goal: FG, mut visited: S) -> Option<T>
+where
+    N: Clone,
+    T: PathTrack<N>,
+    S: StateMap<N, Option<N>>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+    FG: FnMut(&N) -> bool,
+{
+    let mut queue = VecDeque::new();
+
+    visited.insert(start.clone(), None);
+    queue.push_back(start);
+
+    while let Some(node) = queue.pop_front() {
+        if goal(&node) {
+            return Some(reconstruct(node, |n| visited.get(n).cloned().flatten()));
+        }
+        for next in neighbours(&node) {
+            if visited.get(&next).is_none() {
+                visited.insert(next.clone(), Some(node.clone()));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Breadth-first search of everything reachable from `start`, returning the number of steps to each state
+pub fn bfs_distances<N, FN, IN>(start: N, mut neighbours: FN) -> HashMap<N, usize>
+where
+    N: Clone + Eq + Hash,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = N>,
+{
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    distances.insert(start.clone(), 0);
+    queue.push_back((0, start));
+
+    while let Some((depth, node)) = queue.pop_front() {
+        for next in neighbours(&node) {
+            if !distances.contains_key(&next) {
+                distances.insert(next.clone(), depth + 1);
+                queue.push_back((depth + 1, next));
+            }
+        }
+    }
+    distances
+}
+
+/// Dijkstra's search from `start` to the cheapest state satisfying `goal`, returning its cost and the tracked path
+pub fn dijkstra<N, C, T, FN, IN, FG>(start: N, neighbours: FN, goal: FG) -> Option<(C, T)>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    T: PathTrack<N>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FG: FnMut(&N) -> bool,
+{
+    astar(start, neighbours, |_| C::default(), goal)
+}
+
+/// A* search from `start` to the cheapest state satisfying `goal`, returning its cost and the tracked path.
+///
+/// `heuristic` must never overestimate the remaining cost to a goal, or the result may not be optimal.
+pub fn astar<N, C, T, FN, IN, FH, FG>(start: N, mut neighbours: FN, mut heuristic: FH, mut goal: FG) -> Option<(C, T)>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    T: PathTrack<N>,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FH: FnMut(&N) -> C,
+    FG: FnMut(&N) -> bool,
+{
+    let mut costs = HashMap::new();
+    let mut prev = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    costs.insert(start.clone(), C::default());
+    queue.push(Frontier {
+        priority: heuristic(&start),
+        cost: C::default(),
+        node: start,
+    });
+
+    while let Some(Frontier { cost, node, .. }) = queue.pop() {
+        if costs.get(&node).is_some_and(|best| cost > *best) {
+            continue;
+        }
+        if goal(&node) {
+            return Some((cost, reconstruct(node, |n| prev.get(n).cloned())));
+        }
+        for (next, step) in neighbours(&node) {
+            let next_cost = cost + step;
+            if costs.get(&next).is_none_or(|best| next_cost < *best) {
+                costs.insert(next.clone(), next_cost);
+                if T::DOES_WORK {
+                    prev.insert(next.clone(), node.clone());
+                }
+                queue.push(Frontier {
+                    priority: next_cost + heuristic(&next),
+                    cost: next_cost,
+                    node: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Dijkstra's search of everything reachable from `start`, returning the lowest cost to each state
+pub fn dijkstra_distances<N, C, FN, IN>(start: N, mut neighbours: FN) -> HashMap<N, C>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+{
+    let mut costs = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    costs.insert(start.clone(), C::default());
+    queue.push(Frontier {
+        priority: C::default(),
+        cost: C::default(),
+        node: start,
+    });
+
+    while let Some(Frontier { cost, node, .. }) = queue.pop() {
+        if costs.get(&node).is_some_and(|best| cost > *best) {
+            continue;
+        }
+        for (next, step) in neighbours(&node) {
+            let next_cost = cost + step;
+            if costs.get(&next).is_none_or(|best| next_cost < *best) {
+                costs.insert(next.clone(), next_cost);
+                queue.push(Frontier {
+                    priority: next_cost,
+                    cost: next_cost,
+                    node: next,
+                });
+            }
+        }
+    }
+    costs
+}
+
+/// Every shortest path from a search's start to its goal states, as found by [dijkstra_all]
+#[derive(Clone, Debug)]
+pub struct ShortestPaths<N, C> {
+    /// Cost of the shortest paths
+    pub cost: C,
+    /// All goal states reachable at `cost`
+    pub goals: Vec<N>,
+    /// For each state, all of its predecessors on a shortest path to it
+    pub prev: HashMap<N, Vec<N>>,
+}
+
+impl<N: Clone + Eq + Hash, C> ShortestPaths<N, C> {
+    /// All states lying on at least one shortest path, including the start and goals
+    pub fn nodes(&self) -> HashSet<N> {
+        let mut nodes = HashSet::new();
+        let mut stack = self.goals.clone();
+        while let Some(node) = stack.pop() {
+            if nodes.insert(node.clone()) {
+                if let Some(prevs) = self.prev.get(&node) {
+                    stack.extend(prevs.iter().cloned());
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Every distinct shortest path, ordered from start to goal. Paths never visit a state twice, so zero-cost cycles
+    /// aren't followed round. The number of paths can grow exponentially with the number of ties, prefer
+    /// [ShortestPaths::nodes] if the individual paths aren't needed.
+    pub fn paths(&self) -> Vec<Vec<N>> {
+        let mut paths = Vec::new();
+        let mut stack: Vec<Vec<N>> = self.goals.iter().map(|goal| vec![goal.clone()]).collect();
+        while let Some(path) = stack.pop() {
+            match self.prev.get(path.last().unwrap()) {
+                Some(prevs) if !prevs.is_empty() => {
+                    for prev in prevs.iter().filter(|prev| !path.contains(prev)) {
+                        let mut next = path.clone();
+                        next.push(prev.clone());
+                        stack.push(next);
+                    }
+                }
+                _ => {
+                    let mut path = path;
+                    path.reverse();
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+}
+
+/// Dijkstra's search from `start` that records every predecessor on every shortest path to the states satisfying
+/// `goal`. All goal states tied for the lowest cost are returned.
+pub fn dijkstra_all<N, C, FN, IN, FG>(start: N, mut neighbours: FN, mut goal: FG) -> Option<ShortestPaths<N, C>>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FG: FnMut(&N) -> bool,
+{
+    let mut costs = HashMap::new();
+    let mut prev: HashMap<N, Vec<N>> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    let mut goals = Vec::new();
+    let mut best_cost = None;
+
+    costs.insert(start.clone(), C::default());
+    queue.push(Frontier {
+        priority: C::default(),
+        cost: C::default(),
+        node: start.clone(),
+    });
+
+    while let Some(Frontier { cost, node, .. }) = queue.pop() {
+        if best_cost.is_some_and(|best| cost > best) {
+            break;
+        }
+        if costs.get(&node).is_some_and(|best| cost > *best) {
+            continue;
+        }
+        if goal(&node) {
+            best_cost = Some(cost);
+            goals.push(node);
+            continue;
+        }
+        for (next, step) in neighbours(&node) {
+            let next_cost = cost + step;
+            match costs.get(&next).map(|best| next_cost.cmp(best)) {
+                None | Some(Ordering::Less) => {
+                    costs.insert(next.clone(), next_cost);
+                    prev.insert(next.clone(), vec![node.clone()]);
+                    queue.push(Frontier {
+                        priority: next_cost,
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+                // a zero-cost cycle back to the start doesn't make it part of a longer path
+                Some(Ordering::Equal) if next != start => prev.entry(next).or_default().push(node.clone()),
+                Some(Ordering::Equal) => {}
+                Some(Ordering::Greater) => {}
+            }
+        }
+    }
+
+    best_cost.map(|cost| ShortestPaths { cost, goals, prev })
+}
+
+/// Yen's algorithm: up to `k` loopless paths from `start` to states satisfying `goal`, cheapest first, each with its
+/// cost. Paths of equal cost are returned in the order they were found.
+pub fn k_shortest_paths<N, C, FN, IN, FG>(start: N, mut neighbours: FN, mut goal: FG, k: usize) -> Vec<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash,
+    C: Cost,
+    FN: FnMut(&N) -> IN,
+    IN: IntoIterator<Item = (N, C)>,
+    FG: FnMut(&N) -> bool,
+{
+    let mut found: Vec<(C, Vec<N>)> = Vec::new();
+    if k == 0 {
+        return found;
+    }
+    match dijkstra::<_, _, Vec<N>, _, _, _>(start, &mut neighbours, &mut goal) {
+        Some(first) => found.push(first),
+        None => return found,
+    }
+    let mut candidates: Vec<(C, Vec<N>)> = Vec::new();
+
+    while found.len() < k {
+        let last = found.last().unwrap().1.clone();
+        // cost of the path up to each node
+        let mut prefix_costs = vec![C::default()];
+        for pair in last.windows(2) {
+            let step = neighbours(&pair[0])
+                .into_iter()
+                .filter(|(n, _)| *n == pair[1])
+                .map(|(_, c)| c)
+                .min()
+                .unwrap();
+            prefix_costs.push(*prefix_costs.last().unwrap() + step);
+        }
+
+        for i in 0..last.len() - 1 {
+            let (root, spur) = (&last[..i], &last[i]);
+            // leave the spur node by an edge no earlier path sharing this root has taken
+            let banned_edges: HashSet<N> = found
+                .iter()
+                .filter(|(_, path)| path.len() > i + 1 && path[..=i] == last[..=i])
+                .map(|(_, path)| path[i + 1].clone())
+                .collect();
+            let banned_nodes: HashSet<&N> = root.iter().collect();
+            let spur_path = dijkstra::<_, _, Vec<N>, _, _, _>(
+                spur.clone(),
+                |n: &N| {
+                    let from_spur = n == spur;
+                    neighbours(n)
+                        .into_iter()
+                        .filter(|(next, _)| !(banned_nodes.contains(next) || from_spur && banned_edges.contains(next)))
+                        .collect::<Vec<_>>()
+                },
+                &mut goal,
+            );
+            if let Some((cost, spur_path)) = spur_path {
+                let mut path = root.to_vec();
+                path.extend(spur_path);
+                if !candidates.iter().chain(&found).any(|(_, p)| *p == path) {
+                    candidates.push((prefix_costs[i] + cost, path));
+                }
+            }
+        }
+
+        let Some(best) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (cost, _))| *cost)
+            .map(|(i, _)| i)
+        else {
+            break;
+        };
+        found.push(candidates.remove(best));
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0 - 1 - 2 - 3
+    //  \         /
+    //   4 ----- 5
+    fn graph(n: &u32) -> Vec<(u32, u32)> {
+        match n {
+            0 => vec![(1, 1), (4, 1)],
+            1 => vec![(0, 1), (2, 1)],
+            2 => vec![(1, 1), (3, 1)],
+            3 => vec![(2, 1), (5, 2)],
+            4 => vec![(0, 1), (5, 2)],
+            5 => vec![(4, 2), (3, 2)],
+            _ => unreachable!(),
+        }
+    }
+
+    fn unweighted(n: &u32) -> impl Iterator<Item = u32> {
+        graph(n).into_iter().map(|(n, _)| n)
+    }
+
+    #[test]
+    fn bfs_tracks() {
+        assert_eq!(
+            bfs::<_, Vec<_>, _, _, _>(0, unweighted, |n| *n == 3),
+            Some(vec![0, 1, 2, 3])
+        );
+        assert_eq!(
+            bfs::<_, LengthPath, _, _, _>(0, unweighted, |n| *n == 5),
+            Some(LengthPath(3))
+        );
+        assert_eq!(
+            bfs::<_, NoopTrack, _, _, _>(0, unweighted, |n| *n == 5),
+            Some(NoopTrack)
+        );
+        assert_eq!(bfs::<_, NoopTrack, _, _, _>(0, unweighted, |n| *n == 6), None);
+    }
+
+    #[test]
+    fn bfs_grid_store() {
+        let maze: Grid<u8> = "..#\n#..\n...".parse().unwrap();
+        let moves = |pos: &(i64, i64)| {
+            maze.neighbors4(pos)
+                .filter(|(_, v)| **v == b'.')
+                .map(|(c, _)| (c.x, c.y))
+                .collect::<Vec<_>>()
+        };
+        let goal = |pos: &(i64, i64)| *pos == (2, 2);
+        let hashed = bfs::<_, Vec<_>, _, _, _>((0, 0), moves, goal);
+        let dense = bfs_in::<_, Vec<_>, _, _, _, _>((0, 0), moves, goal, maze.same_shape(None));
+        assert_eq!(dense, hashed);
+        assert_eq!(dense.unwrap().len(), 5);
+        assert_eq!(
+            bfs_in::<_, LengthPath, _, _, _, _>((0, 0), moves, |pos| *pos == (0, 2), maze.same_shape(None)),
+            Some(LengthPath(5))
+        );
+    }
+
+    #[test]
+    fn bfs_distance_map() {
+        let distances = bfs_distances(0, unweighted);
+        assert_eq!(distances.len(), 6);
+        assert_eq!(distances[&3], 3);
+        assert_eq!(distances[&5], 2);
+    }
+
+    #[test]
+    fn weighted() {
+        assert_eq!(
+            dijkstra::<_, u32, Vec<_>, _, _, _>(0, graph, |n| *n == 3),
+            Some((3, vec![0, 1, 2, 3]))
+        );
+        assert_eq!(dijkstra::<_, u32, NoopTrack, _, _, _>(0, graph, |n| *n == 6), None);
+        // admissible heuristic: remaining hops on the upper route
+        let heuristic = |n: &u32| if *n <= 3 { 3 - *n } else { 1 };
+        assert_eq!(
+            astar::<_, u32, Vec<_>, _, _, _, _>(0, graph, heuristic, |n| *n == 3),
+            Some((3, vec![0, 1, 2, 3]))
+        );
+        let distances = dijkstra_distances(0, graph);
+        assert_eq!(distances[&5], 3);
+        assert_eq!(distances[&3], 3);
+    }
+
+    #[test]
+    fn all_shortest_paths() {
+        let paths = dijkstra_all(0, graph, |n| *n == 5).unwrap();
+        assert_eq!(paths.cost, 3);
+        assert_eq!(paths.goals, [5]);
+        assert_eq!(paths.paths(), [vec![0, 4, 5]]);
+
+        // diamond, both routes from 0 to 3 are tied
+        let diamond = |n: &u32| match n {
+            0 => vec![(1, 1), (2, 1)],
+            1 | 2 => vec![(3, 1)],
+            _ => vec![],
+        };
+        let paths = dijkstra_all(0, diamond, |n| *n == 3).unwrap();
+        assert_eq!(paths.cost, 2);
+        let mut all = paths.paths();
+        all.sort();
+        assert_eq!(all, [vec![0, 1, 3], vec![0, 2, 3]]);
+        assert_eq!(paths.nodes().len(), 4);
+
+        // zero-cost steps 0 <-> 1 <-> 2 form cycles, including one back to the start
+        let cycles = |n: &u32| match n {
+            0 => vec![(1, 0)],
+            1 => vec![(0, 0), (2, 0), (3, 1)],
+            2 => vec![(1, 0), (3, 1)],
+            _ => vec![],
+        };
+        let paths = dijkstra_all(0, cycles, |n| *n == 3).unwrap();
+        assert_eq!(paths.cost, 1);
+        let mut all = paths.paths();
+        all.sort();
+        assert_eq!(all, [vec![0, 1, 2, 3], vec![0, 1, 3]]);
+    }
+
+    #[test]
+    fn yen() {
+        let paths = k_shortest_paths(0, graph, |n| *n == 3, 5);
+        assert_eq!(paths, [(3, vec![0, 1, 2, 3]), (5, vec![0, 4, 5, 3])]);
+        assert_eq!(k_shortest_paths(0, graph, |n| *n == 3, 1).len(), 1);
+        assert!(k_shortest_paths(0, graph, |n| *n == 3, 0).is_empty());
+        assert!(k_shortest_paths(0, graph, |n| *n == 6, 3).is_empty());
+
+        // the classic example from the Wikipedia article, C=0 D=1 E=2 F=3 G=4 H=5
+        let wiki = |n: &u32| match n {
+            0 => vec![(1, 3), (2, 2)],
+            1 => vec![(3, 4)],
+            2 => vec![(1, 1), (3, 2), (4, 3)],
+            3 => vec![(4, 2), (5, 1)],
+            4 => vec![(5, 2)],
+            _ => vec![],
+        };
+        let paths = k_shortest_paths(0, wiki, |n| *n == 5, 3);
+        assert_eq!(
+            paths,
+            [(5, vec![0, 2, 3, 5]), (7, vec![0, 2, 4, 5]), (8, vec![0, 1, 3, 5])]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Whole-grid transforms (rotation, reflection, cropping, padding) and borrowed sub-grid views.
+
+use crate::{AsCoord2d, Coord2d, Grid, Rect};
+use std::fmt::Debug;
+
+impl<T: Clone + Eq + PartialEq + Debug> Grid<T> {
+    /// Build a grid of the given shape, taking each cell from `f`
+    fn from_fn<F: FnMut(Coord2d) -> T>(width: usize, height: usize, f: F) -> Self {
+        let width = width as i64;
+        Self {
+            data: (0..width * height as i64)
+                .map(|pos| Coord2d {
+                    x: pos % width,
+                    y: pos / width,
+                })
+                .map(f)
+                .collect(),
+            width,
+        }
+    }
+    fn at(&self, x: usize, y: usize) -> T {
+        self.data[y * self.width() + x].clone()
+    }
+
+    /// Rotate a quarter turn clockwise
+    pub fn rotate90(&self) -> Self {
+        let height = self.height();
+        Self::from_fn(height, self.width(), |c| {
+            self.at(c.y as usize, height - 1 - c.x as usize)
+        })
+    }
+    /// Rotate a half turn
+    pub fn rotate180(&self) -> Self {
+        let (width, height) = (self.width(), self.height());
+        Self::from_fn(width, height, |c| {
+            self.at(width - 1 - c.x as usize, height - 1 - c.y as usize)
+        })
+    }
+    /// Rotate a quarter turn anticlockwise
+    pub fn rotate270(&self) -> Self {
+        let width = self.width();
+        Self::from_fn(self.height(), width, |c| {
+            self.at(width - 1 - c.y as usize, c.x as usize)
+        })
+    }
+    /// Mirror left to right
+    pub fn flip_horizontal(&self) -> Self {
+        let width = self.width();
+        Self::from_fn(width, self.height(), |c| {
+            self.at(width - 1 - c.x as usize, c.y as usize)
+        })
+    }
+    /// Mirror top to bottom
+    pub fn flip_vertical(&self) -> Self {
+        let height = self.height();
+        Self::from_fn(self.width(), height, |c| {
+            self.at(c.x as usize, height - 1 - c.y as usize)
+        })
+    }
+    /// Swap rows and columns
+    pub fn transpose(&self) -> Self {
+        Self::from_fn(self.height(), self.width(), |c| self.at(c.y as usize, c.x as usize))
+    }
+    /// A copy of the cells inside `rect`, or `None` if it doesn't lie entirely within the grid
+    pub fn crop(&self, rect: &Rect) -> Option<Self> {
+        self.view(rect).map(|view| view.to_grid())
+    }
+    /// Surround the grid with a border `n` cells wide of `fill`
+    pub fn pad(&self, n: usize, fill: T) -> Self {
+        let mut padded = Self::with_shape(self.width() + 2 * n, self.height() + 2 * n, fill);
+        for (c, val) in self.iter_enumerated() {
+            padded.set(&(c.x + n as i64, c.y + n as i64), val.clone());
+        }
+        padded
+    }
+    /// A grid of the same shape with every cell converted by `f`
+    pub fn map<NT: Clone + Eq + PartialEq + Debug, F: FnMut(&T) -> NT>(&self, f: F) -> Grid<NT> {
+        Grid {
+            data: self.data.iter().map(f).collect(),
+            width: self.width,
+        }
+    }
+    /// A borrowed view of the cells inside `rect`, or `None` if it doesn't lie entirely within the grid
+    pub fn view(&self, rect: &Rect) -> Option<GridView<'_, T>> {
+        if rect.min.x <= rect.max.x && rect.min.y <= rect.max.y && self.is_valid(&rect.min) && self.is_valid(&rect.max)
+        {
+            Some(GridView {
+                grid: self,
+                bounds: *rect,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// A rectangular window onto a [Grid]. Coordinates are relative to the top left corner of the window.
+#[derive(Copy, Clone, Debug)]
+pub struct GridView<'a, T> {
+    grid: &'a Grid<T>,
+    bounds: Rect,
+}
+
+impl<'a, T: Clone + Eq + PartialEq + Debug> GridView<'a, T> {
+    /// The area of the underlying grid this view covers
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+    pub fn width(&self) -> usize {
+        self.bounds.width()
+    }
+    pub fn height(&self) -> usize {
+        self.bounds.height()
+    }
+    /// Convert a view coordinate to a coordinate in the underlying grid
+    fn outer<C: AsCoord2d>(&self, c: &C) -> Coord2d {
+        Coord2d {
+            x: self.bounds.min.x + c.x(),
+            y: self.bounds.min.y + c.y(),
+        }
+    }
+    pub fn is_valid<C: AsCoord2d>(&self, c: &C) -> bool {
+        self.bounds.contains(&self.outer(c))
+    }
+    pub fn get<C: AsCoord2d>(&self, c: &C) -> Option<&'a T> {
+        if self.is_valid(c) {
+            self.grid.get(&self.outer(c))
+        } else {
+            None
+        }
+    }
+    pub fn row(&self, y: i64) -> Option<&'a [T]> {
+        if y >= 0 && (y as usize) < self.height() {
+            self.grid.forward_slice(&self.outer(&(0, y)), self.width() as i64)
+        } else {
+            None
+        }
+    }
+    /// Every coordinate in the view, in row-major order
+    pub fn coords(&self) -> impl Iterator<Item = Coord2d> {
+        let width = self.width() as i64;
+        (0..(self.width() * self.height()) as i64).map(move |pos| Coord2d {
+            x: pos % width,
+            y: pos / width,
+        })
+    }
+    /// Every cell with its coordinate, in row-major order
+    pub fn iter_enumerated(&self) -> impl Iterator<Item = (Coord2d, &'a T)> + '_ {
+        self.coords().map(|c| (c, self.get(&c).unwrap()))
+    }
+    pub fn find(&self, haystack: &T) -> Option<Coord2d> {
+        self.iter_enumerated().find(|(_, val)| *val == haystack).map(|(c, _)| c)
+    }
+    pub fn count(&self, haystack: &T) -> usize {
+        self.iter_enumerated().filter(|(_, val)| *val == haystack).count()
+    }
+    /// The coordinates of every cell equal to `needle`, in row-major order
+    pub fn find_all<'b>(&'b self, needle: &'b T) -> impl Iterator<Item = Coord2d> + 'b {
+        self.positions(move |val| val == needle)
+    }
+    /// The coordinates of every cell satisfying `pred`, in row-major order
+    pub fn positions<'b, F: FnMut(&T) -> bool + 'b>(&'b self, mut pred: F) -> impl Iterator<Item = Coord2d> + 'b {
+        self.iter_enumerated().filter(move |(_, val)| pred(val)).map(|(c, _)| c)
+    }
+    /// The neighbours of `pos` at the given offsets that lie inside the view
+    pub fn neighbors_with<'b, C: AsCoord2d>(
+        &'b self,
+        pos: &C,
+        stencil: &'b [(i64, i64)],
+    ) -> impl Iterator<Item = (Coord2d, &'a T)> + 'b {
+        let (x, y) = (pos.x(), pos.y());
+        stencil.iter().filter_map(move |(x_ofs, y_ofs)| {
+            let neighbor = Coord2d {
+                x: x + x_ofs,
+                y: y + y_ofs,
+            };
+            self.get(&neighbor).map(|val| (neighbor, val))
+        })
+    }
+    /// Copy the viewed cells into a new grid
+    pub fn to_grid(&self) -> Grid<T> {
+        Grid::from_fn(self.width(), self.height(), |c| self.get(&c).unwrap().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NEIGHBORS4;
+
+    fn grid(s: &str) -> Grid<u8> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn rotations() {
+        let g = grid("ABC\nDEF");
+        assert_eq!(g.rotate90(), grid("DA\nEB\nFC"));
+        assert_eq!(g.rotate180(), grid("FED\nCBA"));
+        assert_eq!(g.rotate270(), grid("CF\nBE\nAD"));
+        assert_eq!(g.rotate90().rotate90(), g.rotate180());
+        assert_eq!(g.rotate90().rotate270(), g);
+    }
+
+    #[test]
+    fn reflections() {
+        let g = grid("ABC\nDEF");
+        assert_eq!(g.flip_horizontal(), grid("CBA\nFED"));
+        assert_eq!(g.flip_vertical(), grid("DEF\nABC"));
+        assert_eq!(g.transpose(), grid("AD\nBE\nCF"));
+        assert_eq!(g.transpose().transpose(), g);
+    }
+
+    #[test]
+    fn crop_pad_map() {
+        let g = grid("ABCD\nEFGH\nIJKL");
+        let rect = Rect {
+            min: Coord2d { x: 1, y: 1 },
+            max: Coord2d { x: 2, y: 2 },
+        };
+        assert_eq!(g.crop(&rect), Some(grid("FG\nJK")));
+        let outside = Rect {
+            min: Coord2d { x: 3, y: 1 },
+            max: Coord2d { x: 4, y: 2 },
+        };
+        assert_eq!(g.crop(&outside), None);
+        assert_eq!(grid("AB").pad(1, b'.'), grid("....\n.AB.\n...."));
+        let upper = grid("aBc").map(|c| c.is_ascii_uppercase());
+        assert_eq!(upper.data, [false, true, false]);
+        assert_eq!(upper.width(), 3);
+    }
+
+    #[test]
+    fn views() {
+        let g = grid("ABCD\nEFGH\nIJKL\nFBCG");
+        let view = g
+            .view(&Rect {
+                min: Coord2d { x: 1, y: 1 },
+                max: Coord2d { x: 3, y: 3 },
+            })
+            .unwrap();
+        assert_eq!((view.width(), view.height()), (3, 3));
+        assert_eq!(view.get(&(0, 0)), Some(&b'F'));
+        assert_eq!(view.get(&(3, 0)), None);
+        assert_eq!(view.get(&(-1, 0)), None);
+        assert_eq!(view.row(2), Some(&b"BCG"[..]));
+        assert_eq!(view.row(3), None);
+        assert_eq!(view.find_all(&b'F').collect::<Vec<_>>(), [Coord2d { x: 0, y: 0 }]);
+        assert_eq!(view.count(&b'C'), 1);
+        assert_eq!(
+            view.neighbors_with(&(0, 0), &NEIGHBORS4)
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>(),
+            b"GJ"
+        );
+        assert_eq!(view.to_grid(), grid("FGH\nJKL\nBCG"));
+    }
+}